@@ -0,0 +1,77 @@
+use std::fmt;
+
+/* Minimal arbitrary-precision integer, only used to carry literals that don't
+ * fit in an i64 when the `bigint` feature is enabled. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+	negative: bool,
+	/* Little-endian base 2^32 limbs, without trailing zeroes */
+	limbs: Vec<u32>
+}
+
+impl BigInt {
+	pub fn from_digits(negative: bool, digits: &str, base: u32) -> BigInt {
+		let mut limbs = Vec::<u32>::new();
+
+		for ch in digits.chars() {
+			let mut carry = ch.to_digit(base).unwrap() as u64;
+
+			for limb in limbs.iter_mut() {
+				let value = *limb as u64 * base as u64 + carry;
+				*limb = value as u32;
+				carry = value >> 32;
+			}
+
+			if carry != 0 {
+				limbs.push(carry as u32);
+			}
+		}
+
+		BigInt {
+			negative: negative && !limbs.is_empty(),
+			limbs
+		}
+	}
+}
+
+impl fmt::Display for BigInt {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		const CHUNK: u64 = 1_000_000_000;
+
+		let mut limbs = self.limbs.clone();
+		let mut chunks = Vec::<u64>::new();
+
+		while !limbs.is_empty() {
+			let mut rem = 0u64;
+
+			for limb in limbs.iter_mut().rev() {
+				let value = (rem << 32) | *limb as u64;
+				*limb = (value / CHUNK) as u32;
+				rem = value % CHUNK;
+			}
+
+			chunks.push(rem);
+
+			while limbs.last() == Some(&0) {
+				limbs.pop();
+			}
+		}
+
+		if self.negative {
+			write!(f, "-")?;
+		}
+
+		match chunks.split_last() {
+			None => write!(f, "0"),
+			Some((first, rest)) => {
+				write!(f, "{}", first)?;
+
+				for chunk in rest.iter().rev() {
+					write!(f, "{:09}", chunk)?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use crate::span::{Span, Spanned};
+use crate::util;
+use crate::visit::{self, Visitor};
+use crate::{Binding, Error, Expr, Pos, SourceFile, RESERVED_WORDS};
+
+#[derive(Debug)]
+pub struct CheckError {
+	pub message: String,
+	pub span: Span
+}
+
+impl Error for CheckError {
+	fn position<'a>(&self, file: &SourceFile<'a>) -> Pos<'a> {
+		Pos::from_offset(file, self.span.start)
+	}
+
+	fn message(&self) -> &String {
+		&self.message
+	}
+
+	fn length(&self) -> usize {
+		self.span.end - self.span.start
+	}
+}
+
+pub fn check_duplicate_fns(exprs: &[Spanned<Expr>], file: &SourceFile) -> Result<(), CheckError> {
+	let mut defined = HashMap::<&str, Span>::new();
+
+	for expr in exprs {
+		if let Expr::DefineFn{name, ..} = &expr.node {
+			if let Some(previous) = defined.insert(name, expr.span) {
+				let pos = Pos::from_offset(file, previous.start);
+
+				return Err(CheckError{
+					message: format!("Function `{}` already defined at {}:{}", name, pos.line, pos.column),
+					span: expr.span
+				});
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn is_used(name: &str, exprs: &[&Spanned<Expr>]) -> bool {
+	exprs.iter().any(|expr| visit::collect_variable_refs(expr).iter().any(|(var, _)| *var == name))
+}
+
+#[derive(Default)]
+struct UnusedBindings {
+	unused: Vec<CheckError>
+}
+
+impl UnusedBindings {
+	/* A `let` is in scope for the rest of its body, up to a `let` of the same name */
+	fn check_body(&mut self, body: &[Spanned<Expr>]) {
+		for (i, expr) in body.iter().enumerate() {
+			if let Expr::Let{name, ..} = &expr.node {
+				let scope: Vec<&Spanned<Expr>> = body[i + 1..].iter()
+					.take_while(|expr| !matches!(&expr.node, Expr::Let{name: other, ..} if other == name))
+					.collect();
+
+				if !is_used(name, &scope) {
+					self.unused.push(CheckError{
+						message: format!("Variable `{}` is never used", name),
+						span: expr.span
+					});
+				}
+			}
+		}
+	}
+
+	/* Each `let*` binding is in scope for the following bindings and the body */
+	fn check_bindings(&mut self, bindings: &[Spanned<Binding>], body: &Spanned<Expr>) {
+		for (i, Spanned{node: (name, _), span}) in bindings.iter().enumerate() {
+			let later = &bindings[i + 1..];
+			let shadowed = later.iter().position(|other| &other.node.0 == name);
+
+			let mut scope: Vec<&Spanned<Expr>> = later.iter().map(|other| &other.node.1).collect();
+
+			match shadowed {
+				/* The shadowing binding's own value still sees this one */
+				Some(pos) => scope.truncate(pos + 1),
+				None => scope.push(body)
+			}
+
+			if !is_used(name, &scope) {
+				self.unused.push(CheckError{
+					message: format!("Variable `{}` is never used", name),
+					span: *span
+				});
+			}
+		}
+	}
+}
+
+impl<'ast> Visitor<'ast> for UnusedBindings {
+	fn visit_expr(&mut self, expr: &'ast Spanned<Expr>) {
+		match &expr.node {
+			Expr::Do{exprs: body} | Expr::When{body, ..} | Expr::While{body, ..} | Expr::Loop{body} => self.check_body(body),
+			Expr::LetStar{bindings, body} => self.check_bindings(bindings, body),
+			_ => {}
+		}
+
+		visit::walk_expr(self, expr);
+	}
+}
+
+/* Warns about local variables that are declared but never referenced afterwards,
+ * top-level ones are left alone since other files may use them. */
+pub fn check_unused_bindings(exprs: &[Spanned<Expr>]) -> Vec<CheckError> {
+	let mut checker = UnusedBindings::default();
+
+	for expr in exprs {
+		checker.visit_expr(expr);
+	}
+
+	checker.unused
+}
+
+/* The closest special form to a name, if it's near enough to be a likely typo */
+fn suggest_form(name: &str) -> Option<&'static str> {
+	let limit = if name.chars().count() > 5 { 2 } else { 1 };
+
+	RESERVED_WORDS.iter()
+		.filter(|form| **form != "nil")
+		.map(|form| (util::edit_distance(name, form), *form))
+		.filter(|(distance, _)| *distance > 0 && *distance <= limit)
+		.min()
+		.map(|(_, form)| form)
+}
+
+struct MisspelledForms<'a> {
+	defined: Vec<&'a str>,
+	found: Vec<CheckError>
+}
+
+impl<'a, 'ast> Visitor<'ast> for MisspelledForms<'a> {
+	fn visit_fncall(&mut self, expr: &'ast Spanned<Expr>) {
+		if let Expr::FnCall{name, ..} = &expr.node {
+			if name.chars().count() >= 3 && !self.defined.contains(&name.as_str()) {
+				if let Some(form) = suggest_form(name) {
+					self.found.push(CheckError{
+						message: format!("`{}` is not defined here, did you mean `{}`?", name, form),
+						span: expr.span
+					});
+				}
+			}
+		}
+
+		visit::walk_expr(self, expr);
+	}
+
+	/* Quoted forms are data, anything goes */
+	fn visit_quote(&mut self, _expr: &'ast Spanned<Expr>) {}
+}
+
+/* Warns about calls to undefined functions whose names are close to a special
+ * form, e.g. `(lte x int)`. Only functions defined in the same file are known,
+ * so builtins get flagged too, which is why this is behind `-Wmisspelled-forms`. */
+pub fn check_misspelled_forms(exprs: &[Spanned<Expr>]) -> Vec<CheckError> {
+	let defined = exprs.iter().filter_map(|expr| match &expr.node {
+		Expr::DefineFn{name, ..} => Some(name.as_str()),
+		_ => None
+	}).collect();
+
+	let mut checker = MisspelledForms{defined, found: Vec::new()};
+
+	for expr in exprs {
+		checker.visit_expr(expr);
+	}
+
+	checker.found
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ErrorCode {
+	/* Tokenizer errors */
+	UnknownEscape,
+	UnterminatedString,
+	UnescapedControl,
+	InvalidDigit,
+	IntegerTooLarge,
+	FloatOutOfRange,
+	InvalidCharacter,
+	InvalidName,
+
+	/* Parser errors */
+	ReservedName,
+	Unclosed,
+	UnexpectedToken,
+	NamedArgument,
+	MissingOperand,
+	MalformedArgument,
+	MalformedBinding,
+	EmptyForm,
+	WrongArity,
+	UnquoteOutsideQuasiquote,
+	NestingTooDeep,
+	BareSpecialForm,
+	UnmatchedClosingParen
+}
+
+pub const ALL_CODES: &[ErrorCode] = &[
+	ErrorCode::UnknownEscape,
+	ErrorCode::UnterminatedString,
+	ErrorCode::UnescapedControl,
+	ErrorCode::InvalidDigit,
+	ErrorCode::IntegerTooLarge,
+	ErrorCode::FloatOutOfRange,
+	ErrorCode::InvalidCharacter,
+	ErrorCode::InvalidName,
+	ErrorCode::ReservedName,
+	ErrorCode::Unclosed,
+	ErrorCode::UnexpectedToken,
+	ErrorCode::NamedArgument,
+	ErrorCode::MissingOperand,
+	ErrorCode::MalformedArgument,
+	ErrorCode::MalformedBinding,
+	ErrorCode::EmptyForm,
+	ErrorCode::WrongArity,
+	ErrorCode::UnquoteOutsideQuasiquote,
+	ErrorCode::NestingTooDeep,
+	ErrorCode::BareSpecialForm,
+	ErrorCode::UnmatchedClosingParen
+];
+
+impl ErrorCode {
+	/* Codes never change meaning once assigned, new errors get new ones */
+	pub fn as_str(self) -> &'static str {
+		match self {
+			ErrorCode::UnknownEscape => "E0001",
+			ErrorCode::UnterminatedString => "E0002",
+			ErrorCode::UnescapedControl => "E0003",
+			ErrorCode::InvalidDigit => "E0004",
+			ErrorCode::IntegerTooLarge => "E0005",
+			ErrorCode::InvalidCharacter => "E0006",
+			ErrorCode::InvalidName => "E0007",
+			ErrorCode::FloatOutOfRange => "E0008",
+			ErrorCode::ReservedName => "E0101",
+			ErrorCode::Unclosed => "E0102",
+			ErrorCode::UnexpectedToken => "E0103",
+			ErrorCode::NamedArgument => "E0104",
+			ErrorCode::MissingOperand => "E0105",
+			ErrorCode::MalformedArgument => "E0106",
+			ErrorCode::MalformedBinding => "E0107",
+			ErrorCode::EmptyForm => "E0108",
+			ErrorCode::WrongArity => "E0109",
+			ErrorCode::UnquoteOutsideQuasiquote => "E0110",
+			ErrorCode::NestingTooDeep => "E0111",
+			ErrorCode::BareSpecialForm => "E0112",
+			ErrorCode::UnmatchedClosingParen => "E0113"
+		}
+	}
+
+	pub fn lookup(code: &str) -> Option<ErrorCode> {
+		ALL_CODES.iter().copied().find(|c| c.as_str().eq_ignore_ascii_case(code))
+	}
+
+	pub fn explanation(self) -> &'static str {
+		match self {
+			ErrorCode::UnknownEscape => "\
+A string literal contains a backslash followed by a character that doesn't
+form an escape sequence. The supported escapes are `\\\"`, `\\t` and `\\n`,
+and a backslash at the end of a line continues the string on the next one.
+
+    (print \"a\\qb\")   # error
+    (print \"a\\tb\")   # ok",
+			ErrorCode::UnterminatedString => "\
+A string literal was never closed, the input ended before its closing quote.
+
+    (print \"hello)    # error
+    (print \"hello\")   # ok",
+			ErrorCode::UnescapedControl => "\
+A string literal contains a raw tab, line break or other control character.
+These have to be written as escapes so that they're visible in the source.
+
+    (print \"a	b\")    # error, a raw tab
+    (print \"a\\tb\")   # ok",
+			ErrorCode::InvalidDigit => "\
+A number literal contains a character that isn't a digit in its base.
+Binary literals start with `0b`, octal with `0o` and hexadecimal with `0x`,
+only decimal literals can have a fractional part or an exponent, and that
+part needs a digit before it.
+
+    (print 0b102)   # error
+    (print 0x1.8)   # error
+    (print .5)      # error
+    (print 0x1F)    # ok",
+			ErrorCode::IntegerTooLarge => "\
+An integer literal doesn't fit in 64 bits. Building with the `bigint`
+feature allows arbitrarily large literals.
+
+    (print 99999999999999999999)   # error",
+			ErrorCode::FloatOutOfRange => "\
+A float literal is too large to be represented as a 64-bit float.
+
+    (print 1e999)   # error
+    (print 1e99)    # ok",
+			ErrorCode::InvalidCharacter => "\
+A character that can't appear in a name was found outside of a string.
+Names start with a letter or one of `+-*/<>=!?&._%$^~|:` and may contain
+digits and `'` after that.
+
+    (print a\\b)   # error
+    (print a-b)   # ok",
+			ErrorCode::InvalidName => "\
+A name is malformed, either because it consists only of dots or because it
+is a `:` without a valid keyword name after it.
+
+    (f .. : :1)      # error
+    (f a.b :key 1)   # ok",
+			ErrorCode::ReservedName => "\
+A reserved word was used where a new name is expected. Special forms like
+`fn` and `let`, and the `nil` literal can't be redefined.
+
+    (let do int)   # error
+    (let x int)    # ok",
+			ErrorCode::Unclosed => "\
+The input ended inside of an expression, a `)` is missing somewhere.
+
+    (print (f 1)    # error
+    (print (f 1))   # ok",
+			ErrorCode::UnexpectedToken => "\
+A token appeared where it doesn't make sense, for example a literal where a
+name is expected, or a `]` closing a parenthesised expression.
+
+    (fn 1 (args) 2)   # error
+    (f x]             # error
+    (fn f (args) 2)   # ok",
+			ErrorCode::NamedArgument => "\
+A named argument is malformed. Each `:key` needs a value after it, and all
+positional arguments must come before the named ones.
+
+    (f :key)       # error
+    (f :key 1 2)   # error
+    (f 2 :key 1)   # ok",
+			ErrorCode::MissingOperand => "\
+A special form is missing one of its parts, e.g. a `when` without a
+condition or a quote with nothing after it.
+
+    (when)             # error
+    (when x (print))   # ok",
+			ErrorCode::MalformedArgument => "\
+An argument list is malformed. Each argument is either a name or a
+`(name type)` pair, and `&rest` must be followed by exactly one final one.
+
+    (args (a))               # error
+    (args a &rest b c)       # error
+    (args (a int) &rest b)   # ok",
+			ErrorCode::MalformedBinding => "\
+The bindings of a `let*` are malformed. They're a list of `(name value)` pairs.
+
+    (let* (a 1) a)     # error
+    (let* ((a 1)) a)   # ok",
+			ErrorCode::EmptyForm => "\
+An empty `()` was found. Every parenthesised expression needs an operator or
+special form name, use `nil` for the empty value.
+
+    (print ())    # error
+    (print nil)   # ok",
+			ErrorCode::WrongArity => "\
+A special form was given the wrong number of parts.
+
+    (let x)       # error
+    (let x int)   # ok",
+			ErrorCode::UnquoteOutsideQuasiquote => "\
+A `,` or `,@` was used outside of a quasiquoted template, where there is
+nothing to unquote from.
+
+    (print ,x)         # error
+    (print `(a ,x))    # ok",
+			ErrorCode::NestingTooDeep => "\
+Expressions are nested deeper than the parser allows, which protects it from
+running out of stack. Split the expression up into smaller functions.",
+			ErrorCode::BareSpecialForm => "\
+The name of a special form was used on its own, as if it were a variable.
+Special forms can only be used at the start of a parenthesised expression.
+
+    (print fn)    # error
+    (print 'fn)   # ok, a quoted symbol",
+			ErrorCode::UnmatchedClosingParen => "\
+A `)` was found that doesn't close any expression.
+
+    (print 1))   # error
+    (print 1)    # ok"
+		}
+	}
+}
+
+impl fmt::Display for ErrorCode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
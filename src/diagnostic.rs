@@ -0,0 +1,104 @@
+use std::fmt::{self, Write};
+
+use crate::util::{self, Fill, Paint, Style};
+use crate::{Error, Pos, SourceFile};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+	Error,
+	Warning
+}
+
+impl Severity {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Severity::Error => "error",
+			Severity::Warning => "warning"
+		}
+	}
+
+	fn style(self) -> Style {
+		match self {
+			Severity::Error => Style::Red,
+			Severity::Warning => Style::Yellow
+		}
+	}
+}
+
+const TAB_WIDTH: usize = 4;
+
+/* The display column reached after writing `text` starting at `column` */
+fn advance(text: &str, column: usize) -> usize {
+	text.chars().fold(column, |column, ch| match ch {
+		'\t' => column + TAB_WIDTH - column % TAB_WIDTH,
+		ch => column + util::char_width(ch)
+	})
+}
+
+fn expand_tabs(line: &str) -> String {
+	let mut out = String::new();
+	let mut column = 0;
+
+	for ch in line.chars() {
+		let next = advance(ch.encode_utf8(&mut [0; 4]), column);
+
+		if ch == '\t' {
+			out.push_str(&" ".repeat(next - column));
+		} else {
+			out.push(ch);
+		}
+
+		column = next;
+	}
+
+	out
+}
+
+/* Underlines `length` bytes from the position, stopping at the end of the line.
+ * Tabs and wide characters are accounted for so the underline lines up. */
+fn write_snippet<W: Write>(out: &mut W, pos: &Pos, length: usize, style: Style, color: bool) -> fmt::Result {
+	let gutter = Paint::with(format!(" {} |", pos.line), Style::Dim, color);
+	let line = pos.line_content;
+
+	let start = line.char_indices().nth(pos.column - 1).map_or(line.len(), |(idx, _)| idx);
+	let end = line.char_indices().map(|(idx, _)| idx).find(|&idx| idx >= start + length).unwrap_or(line.len());
+
+	let before = advance(&line[..start], 0);
+	let width = (advance(&line[start..end], before) - before).max(1);
+
+	writeln!(out, "{} {}", gutter, expand_tabs(line))?;
+	writeln!(out, "{} {}{}", gutter, Fill::with(before, ' '), Paint::with(Fill::with(width, '~'), style, color))
+}
+
+/* Writes `name:line:column: severity[code]: message` followed by the offending line
+ * with the offending part underlined, and then the same for each note. */
+pub fn write_error<W: Write, T: Error + ?Sized>(out: &mut W, severity: Severity, name: &str, file: &SourceFile, error: &T, color: bool) -> fmt::Result {
+	let pos = error.position(file);
+	let header = match error.code() {
+		Some(code) => format!("{}[{}]", severity.as_str(), code),
+		None => severity.as_str().to_string()
+	};
+
+	writeln!(out, "{}:{}:{}: {}: {}", name, pos.line, pos.column, Paint::with(header, severity.style(), color), error.message())?;
+	write_snippet(out, &pos, error.length(), severity.style(), color)?;
+
+	for (pos, message) in error.notes(file) {
+		match pos {
+			Some(pos) => {
+				writeln!(out, "{}:{}:{}: {}: {}", name, pos.line, pos.column, Paint::with("note", Style::Dim, color), message)?;
+				write_snippet(out, &pos, 1, Style::Dim, color)?;
+			},
+			None => writeln!(out, "{}: {}: {}", name, Paint::with("hint", Style::Dim, color), message)?
+		}
+	}
+
+	Ok(())
+}
+
+pub fn format_error<T: Error + ?Sized>(severity: Severity, name: &str, file: &SourceFile, error: &T, color: bool) -> String {
+	let mut out = String::new();
+
+	/* Writing into a String can't fail */
+	write_error(&mut out, severity, name, file, error, color).unwrap();
+	out
+}
@@ -0,0 +1,109 @@
+use std::fmt::{self, Write};
+
+use crate::span::Spanned;
+use crate::Expr;
+
+struct DotWriter {
+	out: String,
+	next_id: usize
+}
+
+fn escape(label: &str) -> String {
+	label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl DotWriter {
+	fn node(&mut self, expr: &Spanned<Expr>) -> Result<usize, fmt::Error> {
+		let id = self.next_id;
+		self.next_id += 1;
+
+		let (label, children): (String, Vec<&Spanned<Expr>>) = match &expr.node {
+			Expr::VariableRef{var} => (format!("VariableRef {}", var), vec![]),
+			Expr::IntegerLiteral(..) => (format!("IntegerLiteral {}", expr.node), vec![]),
+			#[cfg(feature = "bigint")]
+			Expr::BigIntegerLiteral(val) => (format!("BigIntegerLiteral {}", val), vec![]),
+			Expr::FloatLiteral(val) => (format!("FloatLiteral {:?}", val), vec![]),
+			Expr::StringLiteral(val) => (format!("StringLiteral {:?}", val), vec![]),
+			Expr::BoolLiteral(val) => (format!("BoolLiteral {}", val), vec![]),
+			Expr::Nil => ("Nil".to_string(), vec![]),
+			Expr::Keyword(name) => (format!("Keyword :{}", name), vec![]),
+			Expr::FnCall{name, args, named} => {
+				let keys: Vec<String> = named.iter().map(|(key, _)| format!(":{}", key)).collect();
+				let label = std::iter::once(format!("FnCall {}", name)).chain(keys).collect::<Vec<_>>().join(" ");
+
+				(label, args.iter().chain(named.iter().map(|(_, value)| value)).collect())
+			},
+			Expr::Args{args, rest} => {
+				let param = |(name, r#type): &(String, Option<String>)| match r#type {
+					Some(r#type) => format!("{}: {}", name, r#type),
+					None => name.to_string()
+				};
+
+				let mut words: Vec<String> = std::iter::once("Args".to_string()).chain(args.iter().map(param)).collect();
+				words.extend(rest.iter().map(|rest| format!("&rest {}", param(rest))));
+
+				(words.join(" "), vec![])
+			},
+			Expr::DefineFn{name, args, body, ..} => (format!("DefineFn {}", name), vec![&**args, &**body]),
+			Expr::DefineMacro{name, args, body} => (format!("DefineMacro {}", name), vec![&**args, &**body]),
+			Expr::Do{exprs} => ("Do".to_string(), exprs.iter().collect()),
+			Expr::Let{name, r#type} => (format!("Let {} {}", name, r#type), vec![]),
+			Expr::Set{name, value} => (format!("Set {}", name), vec![&**value]),
+			Expr::LetStar{bindings, body} => {
+				let names: Vec<&str> = std::iter::once("LetStar").chain(bindings.iter().map(|binding| binding.node.0.as_str())).collect();
+				let mut children: Vec<&Spanned<Expr>> = bindings.iter().map(|binding| &binding.node.1).collect();
+				children.push(body);
+
+				(names.join(" "), children)
+			},
+			Expr::When{cond, body, negated} => {
+				let mut children = vec![&**cond];
+				children.extend(body.iter());
+
+				((if *negated { "Unless" } else { "When" }).to_string(), children)
+			},
+			Expr::While{cond, body} => {
+				let mut children = vec![&**cond];
+				children.extend(body.iter());
+
+				("While".to_string(), children)
+			},
+			Expr::Loop{body} => ("Loop".to_string(), body.iter().collect()),
+			Expr::Module{name, body} => (format!("Module {}", name), body.iter().collect()),
+			Expr::Assert{cond} => ("Assert".to_string(), vec![&**cond]),
+			Expr::AssertEq{left, right} => ("AssertEq".to_string(), vec![&**left, &**right]),
+			Expr::And{exprs} => ("And".to_string(), exprs.iter().collect()),
+			Expr::Or{exprs} => ("Or".to_string(), exprs.iter().collect()),
+			Expr::Quote{expr} => ("Quote".to_string(), vec![&**expr]),
+			Expr::Quasiquote{expr} => ("Quasiquote".to_string(), vec![&**expr]),
+			Expr::Unquote{expr} => ("Unquote".to_string(), vec![&**expr]),
+			Expr::UnquoteSplicing{expr} => ("UnquoteSplicing".to_string(), vec![&**expr])
+		};
+
+		writeln!(self.out, "\tn{} [label=\"{}\"];", id, escape(&label))?;
+
+		for child in children {
+			let child_id = self.node(child)?;
+			writeln!(self.out, "\tn{} -> n{};", id, child_id)?;
+		}
+
+		Ok(id)
+	}
+}
+
+pub fn to_dot(exprs: &[Spanned<Expr>]) -> String {
+	let mut writer = DotWriter {
+		out: String::new(),
+		next_id: 0
+	};
+
+	writer.out.push_str("digraph ast {\n");
+
+	for expr in exprs {
+		/* Writing into a String can't fail */
+		writer.node(expr).unwrap();
+	}
+
+	writer.out.push_str("}\n");
+	writer.out
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use crate::span::{Span, Spanned};
+use crate::{Binding, Error, Expr, Note, Pos, SourceFile};
+
+pub const MAX_EXPANSION_DEPTH: usize = 64;
+
+#[derive(Debug)]
+pub struct ExpandError {
+	pub message: String,
+	pub span: Span,
+	pub notes: Vec<Note>
+}
+
+impl Error for ExpandError {
+	fn position<'a>(&self, file: &SourceFile<'a>) -> Pos<'a> {
+		Pos::from_offset(file, self.span.start)
+	}
+
+	fn message(&self) -> &String {
+		&self.message
+	}
+
+	fn length(&self) -> usize {
+		self.span.end - self.span.start
+	}
+
+	fn notes<'a>(&self, file: &SourceFile<'a>) -> Vec<(Option<Pos<'a>>, &String)> {
+		Note::resolve_all(&self.notes, file)
+	}
+}
+
+struct Macro {
+	params: Vec<String>,
+	/* The contents of the quasiquoted template */
+	template: Spanned<Expr>,
+	span: Span
+}
+
+type Args<'a> = HashMap<&'a str, &'a Spanned<Expr>>;
+
+fn map_all<F>(exprs: Vec<Spanned<Expr>>, f: &mut F) -> Result<Vec<Spanned<Expr>>, ExpandError>
+	where F: FnMut(Spanned<Expr>) -> Result<Spanned<Expr>, ExpandError> {
+	exprs.into_iter().map(|expr| f(expr)).collect()
+}
+
+fn map_box<F>(expr: Spanned<Expr>, f: &mut F) -> Result<Box<Spanned<Expr>>, ExpandError>
+	where F: FnMut(Spanned<Expr>) -> Result<Spanned<Expr>, ExpandError> {
+	Ok(Box::new(f(expr)?))
+}
+
+fn map_bindings<F>(bindings: Vec<(String, Spanned<Expr>)>, f: &mut F) -> Result<Vec<(String, Spanned<Expr>)>, ExpandError>
+	where F: FnMut(Spanned<Expr>) -> Result<Spanned<Expr>, ExpandError> {
+	bindings.into_iter().map(|(name, value)| Ok((name, f(value)?))).collect()
+}
+
+fn map_spanned_bindings<F>(bindings: Vec<Spanned<Binding>>, f: &mut F) -> Result<Vec<Spanned<Binding>>, ExpandError>
+	where F: FnMut(Spanned<Expr>) -> Result<Spanned<Expr>, ExpandError> {
+	bindings.into_iter().map(|Spanned{node: (name, value), span}| Ok(Spanned::new((name, f(value)?), span))).collect()
+}
+
+/* Rebuilds an expression with `f` applied to each of its direct children */
+fn map_children<F>(node: Expr, f: &mut F) -> Result<Expr, ExpandError>
+	where F: FnMut(Spanned<Expr>) -> Result<Spanned<Expr>, ExpandError> {
+	Ok(match node {
+		Expr::FnCall{name, args, named} => Expr::FnCall{name, args: map_all(args, f)?, named: map_bindings(named, f)?},
+		Expr::DefineFn{name, args, body, doc} => Expr::DefineFn{name, args: map_box(*args, f)?, body: map_box(*body, f)?, doc},
+		Expr::DefineMacro{name, args, body} => Expr::DefineMacro{name, args: map_box(*args, f)?, body: map_box(*body, f)?},
+		Expr::Do{exprs} => Expr::Do{exprs: map_all(exprs, f)?},
+		Expr::Set{name, value} => Expr::Set{name, value: map_box(*value, f)?},
+		Expr::LetStar{bindings, body} => Expr::LetStar{bindings: map_spanned_bindings(bindings, f)?, body: map_box(*body, f)?},
+		Expr::When{cond, body, negated} => Expr::When{cond: map_box(*cond, f)?, body: map_all(body, f)?, negated},
+		Expr::While{cond, body} => Expr::While{cond: map_box(*cond, f)?, body: map_all(body, f)?},
+		Expr::Loop{body} => Expr::Loop{body: map_all(body, f)?},
+		Expr::Module{name, body} => Expr::Module{name, body: map_all(body, f)?},
+		Expr::Assert{cond} => Expr::Assert{cond: map_box(*cond, f)?},
+		Expr::AssertEq{left, right} => Expr::AssertEq{left: map_box(*left, f)?, right: map_box(*right, f)?},
+		Expr::And{exprs} => Expr::And{exprs: map_all(exprs, f)?},
+		Expr::Or{exprs} => Expr::Or{exprs: map_all(exprs, f)?},
+		Expr::Quote{expr} => Expr::Quote{expr: map_box(*expr, f)?},
+		Expr::Quasiquote{expr} => Expr::Quasiquote{expr: map_box(*expr, f)?},
+		Expr::Unquote{expr} => Expr::Unquote{expr: map_box(*expr, f)?},
+		Expr::UnquoteSplicing{expr} => Expr::UnquoteSplicing{expr: map_box(*expr, f)?},
+		other => other
+	})
+}
+
+/* Names can't be unquoted, so a parameter in a name position is replaced
+ * with the variable passed for it instead. */
+fn rename(name: &mut String, args: &Args) -> Result<(), ExpandError> {
+	if let Some(arg) = args.get(name.as_str()) {
+		match &arg.node {
+			Expr::VariableRef{var} => *name = var.clone(),
+			_ => return Err(ExpandError{
+				message: format!("Macro parameter `{}` is used as a name, so its argument must be a variable", name),
+				span: arg.span,
+				notes: Vec::new()
+			})
+		}
+	}
+
+	Ok(())
+}
+
+fn substitute(expr: Spanned<Expr>, args: &Args, depth: usize) -> Result<Spanned<Expr>, ExpandError> {
+	let span = expr.span;
+
+	let node = match expr.node {
+		Expr::Unquote{expr} if depth == 0 => {
+			return match &expr.node {
+				Expr::VariableRef{var} if args.contains_key(var.as_str()) => Ok(args[var.as_str()].clone()),
+				_ => Err(ExpandError{
+					message: "Only macro parameters can be unquoted in a macro template".to_string(),
+					span: expr.span,
+					notes: Vec::new()
+				})
+			};
+		},
+		Expr::UnquoteSplicing{..} if depth == 0 => return Err(ExpandError{
+			message: "Splicing is not supported in macro templates".to_string(),
+			span,
+			notes: Vec::new()
+		}),
+		/* Nested quasiquotes are left for whatever evaluates them */
+		Expr::Quasiquote{expr} => Expr::Quasiquote{expr: Box::new(substitute(*expr, args, depth + 1)?)},
+		Expr::Unquote{expr} => Expr::Unquote{expr: Box::new(substitute(*expr, args, depth - 1)?)},
+		Expr::UnquoteSplicing{expr} => Expr::UnquoteSplicing{expr: Box::new(substitute(*expr, args, depth - 1)?)},
+		mut node => {
+			match &mut node {
+				Expr::FnCall{name, ..} | Expr::DefineFn{name, ..} | Expr::Let{name, ..} | Expr::Set{name, ..} | Expr::Module{name, ..} => rename(name, args)?,
+				Expr::LetStar{bindings, ..} => {
+					for Spanned{node: (name, _), ..} in bindings {
+						rename(name, args)?;
+					}
+				},
+				_ => {}
+			}
+
+			map_children(node, &mut |expr| substitute(expr, args, depth))?
+		}
+	};
+
+	Ok(Spanned::new(node, span))
+}
+
+#[derive(Default)]
+struct Expander {
+	macros: HashMap<String, Macro>,
+	depth: usize
+}
+
+impl Expander {
+	fn define(&mut self, name: String, args: Spanned<Expr>, body: Spanned<Expr>, span: Span) -> Result<(), ExpandError> {
+		let params = match args.node {
+			Expr::Args{rest: Some(_), ..} => return Err(ExpandError{
+				message: "Macros don't support `&rest` parameters".to_string(),
+				span: args.span,
+				notes: Vec::new()
+			}),
+			Expr::Args{args, ..} => args.into_iter().map(|(name, _)| name).collect(),
+			_ => return Err(ExpandError{
+				message: "Unexpected expression, was expecting an argument list".to_string(),
+				span: args.span,
+				notes: Vec::new()
+			})
+		};
+
+		let template = match body.node {
+			Expr::Quasiquote{expr} => *expr,
+			_ => return Err(ExpandError{
+				message: format!("The body of macro `{}` must be a quasiquoted template", name),
+				span: body.span,
+				notes: Vec::new()
+			})
+		};
+
+		if let Some(previous) = self.macros.get(&name) {
+			return Err(ExpandError{
+				message: format!("Macro `{}` is already defined", name),
+				span,
+				notes: vec![Note::new_label(previous.span.start, "The previous definition is here".to_string())]
+			});
+		}
+
+		self.macros.insert(name, Macro{params, template, span});
+
+		Ok(())
+	}
+
+	fn expand_call(&self, name: &str, args: &[Spanned<Expr>], named: &[(String, Spanned<Expr>)], span: Span) -> Result<Spanned<Expr>, ExpandError> {
+		let definition = &self.macros[name];
+
+		if let Some((key, value)) = named.first() {
+			return Err(ExpandError{
+				message: format!("Macro `{}` doesn't take named arguments, but got `:{}`", name, key),
+				span: value.span,
+				notes: vec![Note::new_label(definition.span.start, "The macro is defined here".to_string())]
+			});
+		}
+
+		if args.len() != definition.params.len() {
+			let expected = definition.params.len();
+
+			return Err(ExpandError{
+				message: format!("Macro `{}` expects {} argument{}, but got {}", name, expected, if expected == 1 { "" } else { "s" }, args.len()),
+				span,
+				notes: vec![Note::new_label(definition.span.start, "The macro is defined here".to_string())]
+			});
+		}
+
+		let bindings: Args = definition.params.iter().map(String::as_str).zip(args).collect();
+		let expanded = substitute(definition.template.clone(), &bindings, 0)?;
+
+		/* Point at the call site rather than into the template */
+		Ok(Spanned::new(expanded.node, span))
+	}
+
+	fn expand(&mut self, expr: Spanned<Expr>) -> Result<Spanned<Expr>, ExpandError> {
+		let span = expr.span;
+
+		match expr.node {
+			Expr::FnCall{name, args, named} if self.macros.contains_key(&name) => {
+				if self.depth >= MAX_EXPANSION_DEPTH {
+					return Err(ExpandError{
+						message: format!("Macro expansion too deep while expanding `{}`", name),
+						span,
+						notes: Vec::new()
+					});
+				}
+
+				let expanded = self.expand_call(&name, &args, &named, span)?;
+
+				/* The result may itself use macros */
+				self.depth += 1;
+				let result = self.expand(expanded);
+				self.depth -= 1;
+
+				result
+			},
+			Expr::DefineMacro{..} => Err(ExpandError{
+				message: "Macros can only be defined at the top level".to_string(),
+				span,
+				notes: Vec::new()
+			}),
+			/* Quoted forms are data, so calls inside them are left alone */
+			node @ Expr::Quote{..} | node @ Expr::Quasiquote{..} => Ok(Spanned::new(node, span)),
+			node => Ok(Spanned::new(map_children(node, &mut |expr| self.expand(expr))?, span))
+		}
+	}
+}
+
+/* Registers the top-level `defmacro`s and expands every call to them by
+ * substituting the arguments into the macro's template. Expansion is not
+ * hygienic, names introduced by a template can clash with the caller's. */
+pub fn expand_macros(exprs: Vec<Spanned<Expr>>) -> Result<Vec<Spanned<Expr>>, ExpandError> {
+	let mut expander = Expander::default();
+	let mut rest = Vec::new();
+
+	for expr in exprs {
+		match expr.node {
+			Expr::DefineMacro{name, args, body} => expander.define(name, *args, *body, expr.span)?,
+			node => rest.push(Spanned::new(node, expr.span))
+		}
+	}
+
+	rest.into_iter().map(|expr| expander.expand(expr)).collect()
+}
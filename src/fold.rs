@@ -0,0 +1,96 @@
+use crate::lex::Radix;
+use crate::span::{Span, Spanned};
+use crate::{Error, Expr, Pos, SourceFile};
+
+#[derive(Debug)]
+pub struct FoldError {
+	pub message: String,
+	pub span: Span
+}
+
+impl Error for FoldError {
+	fn position<'a>(&self, file: &SourceFile<'a>) -> Pos<'a> {
+		Pos::from_offset(file, self.span.start)
+	}
+
+	fn message(&self) -> &String {
+		&self.message
+	}
+
+	fn length(&self) -> usize {
+		self.span.end - self.span.start
+	}
+}
+
+fn integer(value: Option<i64>) -> Result<Expr, &'static str> {
+	value.map(|value| Expr::IntegerLiteral(value, Radix::Decimal)).ok_or("Integer overflow")
+}
+
+/* Returns None if the call can't be folded, or the reason folding it would fail */
+fn fold_call(name: &str, values: &[i64]) -> Option<Result<Expr, &'static str>> {
+	match (name, values) {
+		("+", _) => Some(integer(values.iter().try_fold(0i64, |acc, &v| acc.checked_add(v)))),
+		("*", _) => Some(integer(values.iter().try_fold(1i64, |acc, &v| acc.checked_mul(v)))),
+		("-", [value]) => Some(integer(value.checked_neg())),
+		("-", [first, rest @ ..]) => Some(integer(rest.iter().try_fold(*first, |acc, &v| acc.checked_sub(v)))),
+		("/" | "mod", [_, rest @ ..]) if rest.contains(&0) => Some(Err("Division by zero")),
+		("/", [first, rest @ ..]) if !rest.is_empty() => Some(integer(rest.iter().try_fold(*first, |acc, &v| acc.checked_div(v)))),
+		("mod", [a, b]) => Some(integer(a.checked_rem(*b))),
+		("<", [a, b]) => Some(Ok(Expr::BoolLiteral(a < b))),
+		(">", [a, b]) => Some(Ok(Expr::BoolLiteral(a > b))),
+		("=", [a, b]) => Some(Ok(Expr::BoolLiteral(a == b))),
+		_ => None
+	}
+}
+
+fn fold_all(exprs: Vec<Spanned<Expr>>) -> Result<Vec<Spanned<Expr>>, FoldError> {
+	exprs.into_iter().map(fold).collect()
+}
+
+fn fold_bindings(bindings: Vec<(String, Spanned<Expr>)>) -> Result<Vec<(String, Spanned<Expr>)>, FoldError> {
+	bindings.into_iter().map(|(name, value)| Ok((name, fold(value)?))).collect()
+}
+
+pub fn fold(expr: Spanned<Expr>) -> Result<Spanned<Expr>, FoldError> {
+	let span = expr.span;
+
+	let node = match expr.node {
+		Expr::FnCall{name, args, named} => {
+			let args = fold_all(args)?;
+			let named = fold_bindings(named)?;
+
+			let values: Option<Vec<i64>> = args.iter().map(|arg| match arg.node {
+				Expr::IntegerLiteral(val, _) => Some(val),
+				_ => None
+			}).collect();
+
+			match values.filter(|_| named.is_empty()).and_then(|values| fold_call(&name, &values)) {
+				Some(Ok(node)) => node,
+				Some(Err(reason)) => return Err(FoldError{
+					message: format!("{} while folding `{}`", reason, name),
+					span
+				}),
+				None => Expr::FnCall{name, args, named}
+			}
+		},
+		Expr::DefineFn{name, args, body, doc} => Expr::DefineFn{name, args: Box::new(fold(*args)?), body: Box::new(fold(*body)?), doc},
+		Expr::Do{exprs} => Expr::Do{exprs: fold_all(exprs)?},
+		Expr::Set{name, value} => Expr::Set{name, value: Box::new(fold(*value)?)},
+		Expr::LetStar{bindings, body} => {
+			let bindings = bindings.into_iter().map(|Spanned{node: (name, value), span}| Ok(Spanned::new((name, fold(value)?), span))).collect::<Result<_, _>>()?;
+			Expr::LetStar{bindings, body: Box::new(fold(*body)?)}
+		},
+		Expr::When{cond, body, negated} => Expr::When{cond: Box::new(fold(*cond)?), body: fold_all(body)?, negated},
+		Expr::While{cond, body} => Expr::While{cond: Box::new(fold(*cond)?), body: fold_all(body)?},
+		Expr::Loop{body} => Expr::Loop{body: fold_all(body)?},
+		Expr::Assert{cond} => Expr::Assert{cond: Box::new(fold(*cond)?)},
+		Expr::AssertEq{left, right} => Expr::AssertEq{left: Box::new(fold(*left)?), right: Box::new(fold(*right)?)},
+		Expr::Module{name, body} => Expr::Module{name, body: fold_all(body)?},
+		Expr::And{exprs} => Expr::And{exprs: fold_all(exprs)?},
+		Expr::Or{exprs} => Expr::Or{exprs: fold_all(exprs)?},
+		/* Quoted forms are data and are left untouched */
+		other => other
+	};
+
+	Ok(Spanned::new(node, span))
+}
@@ -2,6 +2,40 @@ use std::fmt;
 use std::iter::Peekable;
 use std::str::CharIndices;
 
+#[cfg(feature = "bigint")]
+use crate::bigint::BigInt;
+use crate::code::ErrorCode;
+use crate::span::Span;
+use crate::Note;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Radix {
+	Binary,
+	Octal,
+	Decimal,
+	Hexadecimal
+}
+
+impl Radix {
+	pub fn value(self) -> u32 {
+		match self {
+			Radix::Binary => 2,
+			Radix::Octal => 8,
+			Radix::Decimal => 10,
+			Radix::Hexadecimal => 16
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Radix::Binary => "binary",
+			Radix::Octal => "octal",
+			Radix::Decimal => "decimal",
+			Radix::Hexadecimal => "hexadecimal"
+		}
+	}
+}
+
 #[derive(Debug)]
 pub enum TokenKind {
 	LParen,
@@ -9,15 +43,40 @@ pub enum TokenKind {
 	LBracket,
 	RBracket,
 	Quote,
+	Quasiquote,
+	Unquote,
+	UnquoteSplicing,
+	Dot,
 	Name(String),
-	Integer(i64),
-	String(String)
+	Integer(i64, Radix),
+	#[cfg(feature = "bigint")]
+	BigInteger(BigInt),
+	Float(f64),
+	Bool(bool),
+	String(String),
+	Keyword(String),
+	DocComment(String),
+	Comment(String)
+}
+
+impl TokenKind {
+	pub fn is_rparen(&self) -> bool {
+		matches!(self, TokenKind::RParen)
+	}
+
+	pub fn is_rbracket(&self) -> bool {
+		matches!(self, TokenKind::RBracket)
+	}
 }
 
 #[derive(Debug)]
 pub struct Token {
 	pub kind: TokenKind,
-	pub pos: usize
+	pub pos: usize,
+	pub end: usize,
+	/* 1-based, filled in by the Tokenizer once the token is complete */
+	pub line: usize,
+	pub column: usize
 }
 
 impl Token {
@@ -29,32 +88,131 @@ impl Token {
 				'[' => TokenKind::LBracket,
 				']' => TokenKind::RBracket,
 				'\'' => TokenKind::Quote,
+				'`' => TokenKind::Quasiquote,
+				',' => TokenKind::Unquote,
 				_ => unreachable!()
 			},
-			pos
+			pos,
+			end: pos + 1,
+			line: 0,
+			column: 0
+		}
+	}
+
+	fn new_unquote_splicing(pos: usize) -> Token {
+		Token {
+			kind: TokenKind::UnquoteSplicing,
+			pos,
+			end: pos + 2,
+			line: 0,
+			column: 0
+		}
+	}
+
+	fn new_dot(pos: usize) -> Token {
+		Token {
+			kind: TokenKind::Dot,
+			pos,
+			end: pos + 1,
+			line: 0,
+			column: 0
 		}
 	}
 
 	fn new_name(name: String, pos: usize) -> Token {
 		Token {
+			end: pos + name.len(),
 			kind: TokenKind::Name(name),
-			pos
+			pos,
+			line: 0,
+			column: 0
+		}
+	}
+
+	fn new_integer(value: i64, radix: Radix, pos: usize, end: usize) -> Token {
+		Token {
+			kind: TokenKind::Integer(value, radix),
+			pos,
+			end,
+			line: 0,
+			column: 0
 		}
 	}
 
-	fn new_integer(value: i64, pos: usize) -> Token {
+	fn new_float(value: f64, pos: usize, end: usize) -> Token {
 		Token {
-			kind: TokenKind::Integer(value),
-			pos
+			kind: TokenKind::Float(value),
+			pos,
+			end,
+			line: 0,
+			column: 0
 		}
 	}
 
-	fn new_string(value: String, pos: usize) -> Token {
+	fn new_bool(value: bool, pos: usize, end: usize) -> Token {
+		Token {
+			kind: TokenKind::Bool(value),
+			pos,
+			end,
+			line: 0,
+			column: 0
+		}
+	}
+
+	#[cfg(feature = "bigint")]
+	fn new_big_integer(value: BigInt, pos: usize, end: usize) -> Token {
+		Token {
+			kind: TokenKind::BigInteger(value),
+			pos,
+			end,
+			line: 0,
+			column: 0
+		}
+	}
+
+	fn new_string(value: String, pos: usize, end: usize) -> Token {
 		Token {
 			kind: TokenKind::String(value),
-			pos
+			pos,
+			end,
+			line: 0,
+			column: 0
+		}
+	}
+
+	fn new_keyword(name: String, pos: usize) -> Token {
+		Token {
+			end: pos + 1 + name.len(),
+			kind: TokenKind::Keyword(name),
+			pos,
+			line: 0,
+			column: 0
 		}
 	}
+
+	fn new_comment(text: String, pos: usize, end: usize) -> Token {
+		Token {
+			kind: TokenKind::Comment(text),
+			pos,
+			end,
+			line: 0,
+			column: 0
+		}
+	}
+
+	fn new_doc_comment(text: String, pos: usize, end: usize) -> Token {
+		Token {
+			kind: TokenKind::DocComment(text),
+			pos,
+			end,
+			line: 0,
+			column: 0
+		}
+	}
+
+	pub fn span(&self) -> Span {
+		Span::new(self.pos, self.end)
+	}
 }
 
 impl fmt::Display for Token {
@@ -65,53 +223,230 @@ impl fmt::Display for Token {
 			TokenKind::LBracket => "opening bracket",
 			TokenKind::RBracket => "closing bracket",
 			TokenKind::Quote => "quote",
+			TokenKind::Quasiquote => "quasiquote",
+			TokenKind::Unquote => "unquote",
+			TokenKind::UnquoteSplicing => "splicing unquote",
+			TokenKind::Dot => "dot",
 			TokenKind::Name(_) => "name",
-			TokenKind::Integer(_) => "integer",
-			TokenKind::String(_) => "string"
+			TokenKind::Integer(..) => "integer",
+			#[cfg(feature = "bigint")]
+			TokenKind::BigInteger(_) => "integer",
+			TokenKind::Float(_) => "float",
+			TokenKind::Bool(_) => "boolean",
+			TokenKind::String(_) => "string",
+			TokenKind::Keyword(_) => "keyword",
+			TokenKind::DocComment(_) => "doc comment",
+			TokenKind::Comment(_) => "comment"
 		};
 
 		write!(f, "{}", readable_name)
 	}
 }
 
+/* Quotes that aren't escaped with a backslash */
+fn count_quotes(text: &str) -> usize {
+	let mut chars = text.chars();
+	let mut count = 0;
+
+	while let Some(ch) = chars.next() {
+		match ch {
+			'\\' => {
+				chars.next();
+			},
+			'"' => count += 1,
+			_ => {}
+		}
+	}
+
+	count
+}
+
+fn strip_sign(text: &str) -> &str {
+	text.strip_prefix(|ch| ch == '-' || ch == '+').unwrap_or(text)
+}
+
+/* Digits with a fractional part, an exponent or both, as in `-0.5`, `1e9` or `2.5E-3` */
+fn is_float_literal(text: &str) -> bool {
+	let digits = |s: &str| !s.is_empty() && s.chars().all(|ch| ch.is_ascii_digit());
+
+	let (mantissa, exponent) = match strip_sign(text).split_once(|ch| ch == 'e' || ch == 'E') {
+		Some((mantissa, exponent)) => (mantissa, Some(strip_sign(exponent))),
+		None => (strip_sign(text), None)
+	};
+
+	let mantissa_ok = match mantissa.split_once('.') {
+		Some((whole, fraction)) => digits(whole) && digits(fraction),
+		None => digits(mantissa) && exponent.is_some()
+	};
+
+	mantissa_ok && exponent.map_or(true, digits)
+}
+
+/* Besides letters and digits, these may appear anywhere in a name */
+pub const NAME_SYMBOLS: &str = "+-*/<>=!?&._%$^~|:";
+
+pub fn is_ident_start(ch: char) -> bool {
+	ch.is_alphabetic() || NAME_SYMBOLS.contains(ch)
+}
+
+/* Primes are allowed after the first character, as in `x'` */
+pub fn is_ident_continue(ch: char) -> bool {
+	ch.is_alphanumeric() || ch == '\'' || NAME_SYMBOLS.contains(ch)
+}
+
 #[derive(Debug)]
 pub struct TokenizeError {
 	pub message: String,
-	pub pos: usize
+	pub code: ErrorCode,
+	pub pos: usize,
+	/* Secondary positions and messages, each shown below the main one in order */
+	pub notes: Vec<Note>
 }
 
 struct Consumed {
 	this: char,
 	next: Option<char>,
-	pos: usize
+	pos: usize,
+	line: usize,
+	column: usize
+}
+
+#[derive(Clone, Copy)]
+pub struct TokenizerOptions {
+	/* Starts a line comment, or a doc comment when followed by ';' (except
+	 * when it is ';' itself, then there are no doc comments). Has to be a
+	 * character that can't otherwise start a token, see `with_comment_char`. */
+	pub comment_char: char,
+	/* Added to every byte offset, for sources embedded in a larger document.
+	 * Lines and columns are still counted from the start of the source. */
+	pub base_offset: usize,
+	/* Emit line comments as tokens instead of skipping them, e.g. for a formatter */
+	pub keep_comments: bool,
+	/* Allow raw line breaks inside strings, otherwise they have to be written as `\n` */
+	pub multiline_strings: bool
+}
+
+impl Default for TokenizerOptions {
+	fn default() -> TokenizerOptions {
+		TokenizerOptions {
+			comment_char: '#',
+			base_offset: 0,
+			keep_comments: false,
+			multiline_strings: false
+		}
+	}
+}
+
+impl TokenizerOptions {
+	pub fn with_comment_char(self, comment_char: char) -> Result<TokenizerOptions, String> {
+		if !is_valid_comment_char(comment_char) {
+			return Err(format!("`{}` can't be used as the comment character", comment_char.escape_debug()));
+		}
+
+		Ok(TokenizerOptions{comment_char, ..self})
+	}
+}
+
+fn is_valid_comment_char(ch: char) -> bool {
+	/* Anything starting another token would win over it, and letters and digits would cut names and numbers short */
+	!ch.is_whitespace() && !ch.is_alphanumeric() && !matches!(ch, '('|')'|'['|']'|'"'|'\''|'`'|','|'-'|'+')
+}
+
+#[derive(Clone, Copy)]
+enum Recovery {
+	/* Skip the rest of the word */
+	Word,
+	/* Skip to the closing quote of the string being read */
+	String,
+	/* The error already consumed what caused it */
+	Nothing
 }
 
 pub struct Tokenizer<'a> {
-	it: Peekable<CharIndices<'a>>
+	source: &'a str,
+	it: Peekable<CharIndices<'a>>,
+	options: TokenizerOptions,
+	line: usize,
+	column: usize,
+	start: (usize, usize),
+	/* How to skip past the next error, see `recover` */
+	recovery: Recovery,
+	/* Set after the first error, the iterator stops there */
+	done: bool
 }
 
 impl<'a> Tokenizer<'a> {
 	pub fn new_from_source(source: &'a str) -> Tokenizer {
+		Tokenizer::new_from_source_with_options(source, TokenizerOptions::default())
+	}
+
+	pub fn new_from_source_with_options(source: &'a str, options: TokenizerOptions) -> Tokenizer {
+		assert!(is_valid_comment_char(options.comment_char), "Invalid comment character `{}`", options.comment_char.escape_debug());
+
 		Tokenizer {
+			source,
 			it: source.char_indices().peekable(),
+			options,
+			line: 1,
+			column: 1,
+			start: (1, 1),
+			recovery: Recovery::Word,
+			done: false
 		}
 	}
 
+	/* Tokenizes `source` from `start` onwards, with positions relative to all of
+	 * `source`. `start` should be a point between tokens, see `restart_offset`. */
+	pub fn new_from_source_at(source: &'a str, start: usize) -> Tokenizer {
+		let line_start = source[..start].rfind('\n').map_or(0, |pos| pos + 1);
+
+		Tokenizer {
+			line: source[..start].matches('\n').count() + 1,
+			column: source[line_start..start].chars().count() + 1,
+			..Tokenizer::new_from_source_with_options(&source[start..], TokenizerOptions{base_offset: start, ..TokenizerOptions::default()})
+		}
+	}
+
+	fn offset(&mut self) -> usize {
+		self.it.peek().map_or(self.source.len(), |&(pos, _)| pos)
+	}
+
 	fn consume_next(&mut self) -> Option<Consumed> {
 		let (pos, this) = self.it.next()?;
 		let next = self.it.peek().map(|v| v.1);
+		let (line, column) = (self.line, self.column);
+
+		if this == '\n' {
+			self.line += 1;
+			self.column = 1;
+		} else {
+			self.column += 1;
+		}
 
-		Some(Consumed{this, next, pos})
+		Some(Consumed{this, next, pos, line, column})
 	}
 
-	pub fn tokenize(&mut self) -> Result<Vec<Token>, TokenizeError> {
-		let mut tokens = Vec::<Token>::new();
+	fn next_token(&mut self) -> Result<Option<Token>, TokenizeError> {
+		if self.offset() == 0 && (self.line, self.column) == (1, 1) && self.source.starts_with("#!") {
+			while let Some(c) = self.consume_next() {
+				if c.this == '\n' {
+					break;
+				}
+			}
+		}
 
 		while let Some(c) = self.consume_next() {
+			self.start = (c.line, c.column);
+
 			match (c.this, c.next) {
-				('('|')'|'['|']'|'\'', _) => tokens.push(Token::new_simple(c.this, c.pos)),
+				(',', Some('@')) => {
+					self.consume_next();
+					return Ok(Some(Token::new_unquote_splicing(c.pos)));
+				},
+				('('|')'|'['|']'|'\''|'`'|',', _) => return Ok(Some(Token::new_simple(c.this, c.pos))),
 				('"', _) => {
 					let mut content = String::new();
+					self.recovery = Recovery::String;
 
 					loop {
 						let s = self.consume_next();
@@ -124,19 +459,65 @@ impl<'a> Tokenizer<'a> {
 										'"' => '"',
 										't' => '\t',
 										'n' => '\n',
+										/* A line continuation, drop the line break and the indentation after it */
+										'\n' | '\r' => {
+											if next == '\r' && matches!(self.it.peek(), Some((_, '\n'))) {
+												self.consume_next();
+											}
+
+											while matches!(self.it.peek(), Some((_, ' ' | '\t'))) {
+												self.consume_next();
+											}
+
+											continue;
+										},
 										_ => return Err(TokenizeError{
 											message: format!("Unknown escape sequence '\\{}'", next),
-											pos: c.pos
+											code: ErrorCode::UnknownEscape,
+											pos: c.pos,
+											notes: Vec::new()
 										})
 									}
 								} else {
 									return Err(TokenizeError{
 										message: "Unexpected end of file".to_string(),
-										pos: c.pos
+										code: ErrorCode::UnterminatedString,
+										pos: c.pos,
+										notes: Vec::new()
 									});
 								}
 							} else if c.this == '"' {
 								break;
+							} else if self.options.multiline_strings && c.this == '\r' && c.next == Some('\n') {
+								/* Line breaks inside strings are always stored as '\n' */
+								continue;
+							} else if c.this.is_control() && !(self.options.multiline_strings && c.this == '\n') {
+								let message = match c.this {
+									'\t' => "Unescaped tab in string literal, use `\\t` instead".to_string(),
+									'\n' | '\r' => "Unescaped line break in string literal, use `\\n` instead".to_string(),
+									_ => format!("Unescaped control character U+{:04X} in string literal", c.this as u32)
+								};
+
+								/* Either the closing quote is missing, or the string was meant to span
+								 * lines. Only in the latter case will the next line have an odd number
+								 * of quotes, then recovery skips to the one closing this string.
+								 * Otherwise the next line is read as usual, from its very start. */
+								if matches!(c.this, '\n' | '\r') {
+									let offset = self.offset();
+									let rest = &self.source[offset..];
+									let next_line = rest.strip_prefix('\n').unwrap_or(rest).split('\n').next().unwrap_or("");
+
+									if count_quotes(next_line) % 2 == 0 {
+										self.recovery = Recovery::Nothing;
+									}
+								}
+
+								return Err(TokenizeError{
+									message,
+									code: ErrorCode::UnescapedControl,
+									pos: c.pos,
+									notes: Vec::new()
+								});
 							} else {
 								c.this
 							};
@@ -145,74 +526,367 @@ impl<'a> Tokenizer<'a> {
 						} else {
 							return Err(TokenizeError{
 								message: "Unterminated string".to_string(),
-								pos: c.pos
+								code: ErrorCode::UnterminatedString,
+								pos: self.source.len(),
+								notes: vec![Note::new_label(c.pos, "The string starts here".to_string())]
 							});
 						}
 					}
 
-					tokens.push(Token::new_string(content, c.pos));
+					self.recovery = Recovery::Word;
+
+					let end = self.offset();
+					return Ok(Some(Token::new_string(content, c.pos, end)));
 				},
-				('0'..='9', _)|('-', Some('0'..='9')) => {
+				('0'..='9', _)|('-'|'+', Some('0'..='9')) => {
 					let sign: i64 = if c.this == '-' { -1 } else { 1 };
-					let mut value: i64 = if c.this == '-' { 0 } else { c.this.to_digit(10).unwrap() as i64 };
+					let mut digits = String::new();
 
-					let base = if c.this == '0' && c.next == Some('x') {
-						self.consume_next();
-						16
-					} else {
-						10
+					let radix = match (c.this, c.next) {
+						('0', Some('x')) => Radix::Hexadecimal,
+						('0', Some('o')) => Radix::Octal,
+						('0', Some('b')) => Radix::Binary,
+						_ => Radix::Decimal
 					};
 
+					/* The `0` of a based prefix isn't one of its digits */
+					if radix != Radix::Decimal {
+						self.consume_next();
+					} else if !matches!(c.this, '-'|'+') {
+						digits.push(c.this);
+					}
+
+					let base = radix.value();
+
+					/* A number ends wherever a name would, anything else directly after the
+					 * digits (like the `abc` in `123abc`) makes the whole literal malformed. */
+					let mut invalid = None;
+
 					while let Some((_, ch)) = self.it.peek() {
-						if ch.is_whitespace() || matches!(ch, ')'|']') {
+						if ch.is_whitespace() || matches!(ch, '('|')'|'['|']'|'"') || *ch == self.options.comment_char {
 							break;
 						}
 
 						let s = self.consume_next().unwrap();
 
-						if !s.this.is_digit(base) {
+						if invalid.is_some() {
+							continue;
+						}
+
+						if s.this.is_digit(base) {
+							digits.push(s.this);
+						} else {
+							invalid = Some(s);
+						}
+					}
+
+					let end = self.offset();
+					let literal = &self.source[c.pos..end];
+
+					if invalid.is_some() && radix == Radix::Decimal && is_float_literal(literal) {
+						let value: f64 = literal.parse().unwrap();
+
+						if value.is_infinite() {
 							return Err(TokenizeError{
-								message: "Unexpected character in integer literal".to_string(),
-								pos: s.pos
+								message: format!("Float literal `{}` is out of range", literal),
+								code: ErrorCode::FloatOutOfRange,
+								pos: c.pos,
+								notes: Vec::new()
 							});
 						}
 
-						value *= base as i64;
-						value += s.this.to_digit(base).unwrap() as i64;
+						return Ok(Some(Token::new_float(value, c.pos, end)));
+					}
+
+					if let Some(s) = invalid {
+						/* There are no based floats, don't let `0x1.8` read as something else */
+						let message = if s.this == '.' && radix != Radix::Decimal {
+							format!("Fractional digits are not allowed in {} literal `{}`", radix.name(), literal)
+						} else if matches!(s.this, '.' | 'e' | 'E') && radix == Radix::Decimal {
+							format!("Malformed float literal `{}`", literal)
+						} else {
+							format!("Invalid digit '{}' in {} literal `{}`", s.this, radix.name(), literal)
+						};
+
+						return Err(TokenizeError{
+							message,
+							code: ErrorCode::InvalidDigit,
+							pos: s.pos,
+							notes: Vec::new()
+						});
+					}
+
+					if digits.is_empty() {
+						return Err(TokenizeError{
+							message: format!("Missing digits after `{}`", literal),
+							code: ErrorCode::InvalidDigit,
+							pos: c.pos,
+							notes: Vec::new()
+						});
+					}
+
+					/* Accumulate towards the sign so that i64::MIN is still representable */
+					let value = digits.chars().try_fold(0i64, |value, ch| {
+						value.checked_mul(base as i64)?.checked_add(sign * ch.to_digit(base).unwrap() as i64)
+					});
+
+					match value {
+						Some(value) => return Ok(Some(Token::new_integer(value, radix, c.pos, end))),
+						#[cfg(feature = "bigint")]
+						None => return Ok(Some(Token::new_big_integer(BigInt::from_digits(sign < 0, &digits, base), c.pos, end))),
+						#[cfg(not(feature = "bigint"))]
+						None => return Err(TokenizeError{
+							message: format!("Integer literal `{}` is too large", literal),
+							code: ErrorCode::IntegerTooLarge,
+							pos: c.pos,
+							notes: Vec::new()
+						})
 					}
+				},
+				(ch, Some(';')) if ch == self.options.comment_char && ch != ';' => {
+					self.consume_next();
+
+					let mut text = String::new();
 
-					value *= sign;
+					while let Some((_, ch)) = self.it.peek() {
+						if *ch == '\n' {
+							break;
+						}
+
+						text.push(self.consume_next().unwrap().this);
+					}
 
-					tokens.push(Token::new_integer(value, c.pos));
+					let end = self.offset();
+					return Ok(Some(Token::new_doc_comment(text.trim().to_string(), c.pos, end)));
 				},
-				('#', _) => {
-					while let Some(c) = self.consume_next() {
-						if c.this == '\n' {
+				(ch, _) if ch == self.options.comment_char => {
+					let mut text = String::new();
+
+					while let Some((_, ch)) = self.it.peek() {
+						if *ch == '\n' {
 							break;
 						}
+
+						text.push(self.consume_next().unwrap().this);
+					}
+
+					if self.options.keep_comments {
+						/* The carriage return of a CRLF line ending isn't part of the comment */
+						let text = text.strip_suffix('\r').unwrap_or(&text).to_string();
+						let end = c.pos + self.options.comment_char.len_utf8() + text.len();
+
+						return Ok(Some(Token::new_comment(text, c.pos, end)));
 					}
 				},
+				/* Anything else starts a name, which runs until whitespace, a parenthesis
+				 * or bracket, a quote or the comment character. A sign directly followed
+				 * by a digit is an integer instead (handled above), so `-`, `+` and `-a`
+				 * are names while `-1` and `+1` are not. Names are made of letters, digits
+				 * and NAME_SYMBOLS, may not start with a digit (see is_ident_start and
+				 * is_ident_continue) and may not consist only of dots. */
 				_ if !c.this.is_whitespace() => {
+					if c.this == '\\' {
+						return Err(TokenizeError{
+							message: "Unexpected '\\' outside of a string".to_string(),
+							code: ErrorCode::InvalidCharacter,
+							pos: c.pos,
+							notes: Vec::new()
+						});
+					}
+
+					if !is_ident_start(c.this) {
+						return Err(TokenizeError{
+							message: format!("Unexpected character '{}', names must start with a letter or one of `{}`", c.this, NAME_SYMBOLS),
+							code: ErrorCode::InvalidCharacter,
+							pos: c.pos,
+							notes: Vec::new()
+						});
+					}
+
 					let mut name = String::new();
 
 					name.push(c.this);
 
 					while let Some((_, ch)) = self.it.peek() {
-						if ch.is_whitespace() || matches!(ch, '('|')'|'"') {
+						if ch.is_whitespace() || matches!(ch, '('|')'|'['|']'|'"') || *ch == self.options.comment_char {
 							break;
 						}
 
 						let s = self.consume_next().unwrap();
 
+						if s.this == '\\' {
+							return Err(TokenizeError{
+								message: "Unexpected '\\' in name".to_string(),
+								code: ErrorCode::InvalidCharacter,
+								pos: s.pos,
+								notes: Vec::new()
+							});
+						}
+
+						if !is_ident_continue(s.this) {
+							return Err(TokenizeError{
+								message: format!("Unexpected character '{}' in name", s.this),
+								code: ErrorCode::InvalidCharacter,
+								pos: s.pos,
+								notes: Vec::new()
+							});
+						}
+
 						name.push(s.this);
 					}
 
-					tokens.push(Token::new_name(name, c.pos));
+					/* A lone `.` is its own token, `a.b` is still a single name. Digits
+					 * are handled above, so `1.5` is never considered here. */
+					if name == "." {
+						return Ok(Some(Token::new_dot(c.pos)));
+					}
+
+					if name.chars().all(|ch| ch == '.') {
+						return Err(TokenizeError{
+							message: format!("`{}` is not a valid name", name),
+							code: ErrorCode::InvalidName,
+							pos: c.pos,
+							notes: Vec::new()
+						});
+					}
+
+					/* Would otherwise be a name, but `.5` and `-.5e1` are clearly meant as floats */
+					if strip_sign(&name).starts_with('.') && strip_sign(&name)[1..].starts_with(|ch: char| ch.is_ascii_digit()) {
+						return Err(TokenizeError{
+							message: format!("Float literal `{}` needs a leading digit, like `{}`", name, name.replacen('.', "0.", 1)),
+							code: ErrorCode::InvalidDigit,
+							pos: c.pos,
+							notes: Vec::new()
+						});
+					}
+
+					if name == "true" || name == "false" {
+						let end = self.offset();
+						return Ok(Some(Token::new_bool(name == "true", c.pos, end)));
+					}
+
+					/* Only a leading ':' makes a keyword, names like `a:b` are left alone */
+					if let Some(keyword) = name.strip_prefix(':') {
+						if keyword.is_empty() {
+							return Err(TokenizeError{
+								message: "Expected a keyword name after ':'".to_string(),
+								code: ErrorCode::InvalidName,
+								pos: c.pos,
+								notes: Vec::new()
+							});
+						}
+
+						/* Keyword names follow the rules for names, so `::` and `:1` aren't keywords */
+						if keyword.starts_with(|ch: char| ch == ':' || !is_ident_start(ch)) {
+							return Err(TokenizeError{
+								message: format!("Malformed keyword `{}`, its name must start with a letter or a symbol other than ':'", name),
+								code: ErrorCode::InvalidName,
+								pos: c.pos,
+								notes: Vec::new()
+							});
+						}
+
+						return Ok(Some(Token::new_keyword(keyword.to_string(), c.pos)));
+					} else {
+						return Ok(Some(Token::new_name(name, c.pos)));
+					}
 				},
 				_ => {}
 			}
 		}
 
-		Ok(tokens)
+		Ok(None)
+	}
+
+	pub fn tokenize(&mut self) -> Result<Vec<Token>, TokenizeError> {
+		self.by_ref().collect()
+	}
+
+	/* Skips past whatever caused an error, so that tokenizing can carry on.
+	 * Errors in a string skip to its closing quote, others to the end of the word. */
+	fn recover(&mut self) {
+		match std::mem::replace(&mut self.recovery, Recovery::Word) {
+			Recovery::Word => {
+				while let Some((_, ch)) = self.it.peek() {
+					if ch.is_whitespace() || matches!(ch, '('|')'|'['|']'|'"') || *ch == self.options.comment_char {
+						break;
+					}
+
+					self.consume_next();
+				}
+			},
+			Recovery::String => {
+				while let Some(c) = self.consume_next() {
+					match c.this {
+						'\\' => {
+							self.consume_next();
+						},
+						'"' => break,
+						_ => {}
+					}
+				}
+			},
+			Recovery::Nothing => {}
+		}
+	}
+
+	/* The next token or error, with its position and line filled in */
+	fn next_shifted(&mut self) -> Option<Result<Token, TokenizeError>> {
+		let result = self.next_token().transpose();
+		let (line, column) = self.start;
+		let base = self.options.base_offset;
+
+		result.map(|result| match result {
+			Ok(tok) => Ok(Token{pos: tok.pos + base, end: tok.end + base, line, column, ..tok}),
+			Err(e) => Err(TokenizeError{pos: e.pos + base, notes: e.notes.into_iter().map(|note| Note{pos: note.pos.map(|pos| pos + base), ..note}).collect(), ..e})
+		})
+	}
+
+	/* Like tokenize, but carries on after errors, returning every token that
+	 * could be read along with all of the errors */
+	pub fn tokenize_partial(&mut self) -> (Vec<Token>, Vec<TokenizeError>) {
+		let mut tokens = Vec::new();
+		let mut errors = Vec::new();
+
+		while let Some(result) = self.next_shifted() {
+			match result {
+				Ok(tok) => tokens.push(tok),
+				Err(e) => {
+					errors.push(e);
+					self.recover();
+				}
+			}
+		}
+
+		(tokens, errors)
+	}
+}
+
+/* Returns an offset before `edit_start` that is safe to re-tokenize an edited
+ * source from, given the tokens of the source before the edit. The end of a
+ * token that the edit can't have touched is never inside a string or comment. */
+pub fn restart_offset(tokens: &[Token], edit_start: usize) -> usize {
+	match tokens.partition_point(|tok| tok.end < edit_start) {
+		0 => 0,
+		i => tokens[i - 1].end
+	}
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+	type Item = Result<Token, TokenizeError>;
+
+	/* Iteration stops after the first error, see `tokenize_partial` to carry on past them */
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+
+		let result = self.next_shifted();
+
+		if !matches!(result, Some(Ok(_))) {
+			self.done = true;
+		}
+
+		result
 	}
 }
@@ -0,0 +1,1224 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::{Filter, Peekable};
+use std::slice::Iter;
+
+#[cfg(feature = "bigint")]
+pub mod bigint;
+pub mod check;
+pub mod code;
+pub mod diagnostic;
+pub mod dot;
+pub mod expand;
+pub mod fold;
+pub mod lex;
+pub mod span;
+pub mod util;
+pub mod visit;
+
+use code::ErrorCode;
+use span::Spanned;
+
+/* A source and the offset each of its lines starts at, built once so that
+ * every lookup is a binary search instead of a walk over the whole source.
+ * Lines and columns are 1-based, columns count characters. Offsets are
+ * relative to `base_offset`, like the positions of a tokenizer given it. */
+pub struct SourceFile<'a> {
+	source: &'a str,
+	base_offset: usize,
+	line_starts: Vec<usize>
+}
+
+impl<'a> SourceFile<'a> {
+	pub fn new_from_source(source: &'a str) -> SourceFile<'a> {
+		SourceFile::new_from_source_with_base(source, 0)
+	}
+
+	pub fn new_from_source_with_base(source: &'a str, base_offset: usize) -> SourceFile<'a> {
+		let mut line_starts = vec![base_offset];
+
+		for (idx, ch) in source.char_indices() {
+			if ch == '\n' {
+				line_starts.push(base_offset + idx + 1);
+			}
+		}
+
+		SourceFile { source, base_offset, line_starts }
+	}
+
+	pub fn source(&self) -> &'a str {
+		self.source
+	}
+
+	pub fn base_offset(&self) -> usize {
+		self.base_offset
+	}
+
+	/* One past the last offset in this file */
+	pub fn end_offset(&self) -> usize {
+		self.base_offset + self.source.len()
+	}
+
+	pub fn line_count(&self) -> usize {
+		self.line_starts.len()
+	}
+
+	pub fn line_of(&self, offset: usize) -> usize {
+		let offset = offset.max(self.base_offset).min(self.end_offset());
+		let line = match self.line_starts.binary_search(&offset) {
+			Ok(line) => line,
+			Err(line) => line - 1
+		};
+
+		/* An offset at the very end of a file ending in a newline would land on
+		 * the empty line after it, point just past the last line instead. */
+		if line > 0 && offset == self.end_offset() && self.line_starts[line] == offset {
+			line
+		} else {
+			line + 1
+		}
+	}
+
+	pub fn line_start(&self, line: usize) -> usize {
+		self.line_starts[line - 1]
+	}
+
+	pub fn line_content(&self, line: usize) -> &'a str {
+		let start = self.line_starts[line - 1] - self.base_offset;
+		let end = self.line_starts.get(line).map_or(self.source.len(), |&next| next - self.base_offset - 1);
+
+		let content = &self.source[start..end];
+		content.strip_suffix('\r').unwrap_or(content)
+	}
+
+	/* Offsets past the end of a line (i.e. at its line break) clamp to just after its last character */
+	pub fn line_column(&self, offset: usize) -> (usize, usize) {
+		let line = self.line_of(offset);
+		let line_content = self.line_content(line);
+		let start = self.line_start(line);
+		let end = offset.max(start).min(start + line_content.len());
+
+		(line, self.source[start - self.base_offset..end - self.base_offset].chars().count() + 1)
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FileId(usize);
+
+/* Lays out every file in one offset space so that a position alone says which
+ * file it's in. Each file should be tokenized with `base_offset` set to its
+ * `SourceFile::base_offset`, its spans then resolve through this map. */
+#[derive(Default)]
+pub struct SourceMap<'a> {
+	files: Vec<(String, SourceFile<'a>)>
+}
+
+impl<'a> SourceMap<'a> {
+	pub fn add(&mut self, name: &str, source: &'a str) -> FileId {
+		/* Leave a gap so the end of one file isn't the start of the next */
+		let base_offset = self.files.last().map_or(0, |(_, file)| file.end_offset() + 1);
+
+		self.files.push((name.to_string(), SourceFile::new_from_source_with_base(source, base_offset)));
+		FileId(self.files.len() - 1)
+	}
+
+	pub fn name(&self, id: FileId) -> &str {
+		&self.files[id.0].0
+	}
+
+	pub fn file(&self, id: FileId) -> &SourceFile<'a> {
+		&self.files[id.0].1
+	}
+
+	pub fn file_of(&self, offset: usize) -> Option<FileId> {
+		let idx = self.files.partition_point(|(_, file)| file.end_offset() < offset);
+
+		match self.files.get(idx) {
+			Some((_, file)) if file.base_offset() <= offset => Some(FileId(idx)),
+			_ => None
+		}
+	}
+
+	pub fn resolve(&self, offset: usize) -> Option<(FileId, Pos<'a>)> {
+		let id = self.file_of(offset)?;
+		Some((id, Pos::from_offset(self.file(id), offset)))
+	}
+}
+
+pub struct Pos<'a> {
+	pub line: usize,
+	pub column: usize,
+	/* The byte offset into the source */
+	pub offset: usize,
+
+	pub line_content: &'a str
+}
+
+impl<'a> Pos<'a> {
+	pub fn from_offset(file: &SourceFile<'a>, pos: usize) -> Pos<'a> {
+		let (line, column) = file.line_column(pos);
+		let line_content = file.line_content(line);
+
+		Pos { line, column, offset: pos.max(file.base_offset()).min(file.line_start(line) + line_content.len()), line_content }
+	}
+
+	/* For tokens, which already know where they start */
+	pub fn from_line_column(file: &SourceFile<'a>, line: usize, column: usize) -> Pos<'a> {
+		let line_content = file.line_content(line);
+		let offset = line_content.char_indices().nth(column - 1).map_or(line_content.len(), |(idx, _)| idx);
+
+		Pos { line, column, offset: file.line_start(line) + offset, line_content }
+	}
+}
+
+/* A parameter name and its optional type */
+pub type Param = (String, Option<String>);
+
+/* A `let*` binding's name and value, spanned over its whole `(name value)` pair */
+pub type Binding = (String, Spanned<Expr>);
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+	VariableRef{var: String},
+	IntegerLiteral(i64, lex::Radix),
+	#[cfg(feature = "bigint")]
+	BigIntegerLiteral(bigint::BigInt),
+	FloatLiteral(f64),
+	StringLiteral(String),
+	BoolLiteral(bool),
+	/* The empty value, e.g. what an empty `(do)` will evaluate to */
+	Nil,
+	Keyword(String),
+	FnCall{name: String, args: Vec<Spanned<Expr>>, named: Vec<(String, Spanned<Expr>)>},
+	Args{args: Vec<Param>, rest: Option<Param>},
+	DefineFn{name: String, args: Box<Spanned<Expr>>, body: Box<Spanned<Expr>>, doc: Option<String>},
+	DefineMacro{name: String, args: Box<Spanned<Expr>>, body: Box<Spanned<Expr>>},
+	Do{exprs: Vec<Spanned<Expr>>},
+	Let{name: String, r#type: String},
+	Set{name: String, value: Box<Spanned<Expr>>},
+	LetStar{bindings: Vec<Spanned<Binding>>, body: Box<Spanned<Expr>>},
+	When{cond: Box<Spanned<Expr>>, body: Vec<Spanned<Expr>>, negated: bool},
+	While{cond: Box<Spanned<Expr>>, body: Vec<Spanned<Expr>>},
+	Loop{body: Vec<Spanned<Expr>>},
+	Module{name: String, body: Vec<Spanned<Expr>>},
+	Assert{cond: Box<Spanned<Expr>>},
+	AssertEq{left: Box<Spanned<Expr>>, right: Box<Spanned<Expr>>},
+	/* Short-circuiting, an empty `and` is truthy and an empty `or` is falsy */
+	And{exprs: Vec<Spanned<Expr>>},
+	Or{exprs: Vec<Spanned<Expr>>},
+	Quote{expr: Box<Spanned<Expr>>},
+	Quasiquote{expr: Box<Spanned<Expr>>},
+	Unquote{expr: Box<Spanned<Expr>>},
+	UnquoteSplicing{expr: Box<Spanned<Expr>>},
+}
+
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+	for item in items {
+		write!(f, " {}", item)?;
+	}
+
+	Ok(())
+}
+
+impl fmt::Display for Expr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Expr::VariableRef{var} => write!(f, "{}", var),
+			Expr::IntegerLiteral(val, radix) => {
+				let sign = if *val < 0 { "-" } else { "" };
+				let abs = val.unsigned_abs();
+
+				match radix {
+					lex::Radix::Binary => write!(f, "{}0b{:b}", sign, abs),
+					lex::Radix::Octal => write!(f, "{}0o{:o}", sign, abs),
+					lex::Radix::Decimal => write!(f, "{}", val),
+					lex::Radix::Hexadecimal => write!(f, "{}0x{:X}", sign, abs)
+				}
+			},
+			#[cfg(feature = "bigint")]
+			Expr::BigIntegerLiteral(val) => write!(f, "{}", val),
+			/* Debug always keeps a fractional part, so this reads back as a float */
+			Expr::FloatLiteral(val) => write!(f, "{:?}", val),
+			Expr::StringLiteral(val) => {
+				let escaped = val.replace('"', "\\\"").replace('\t', "\\t").replace('\n', "\\n");
+				write!(f, "\"{}\"", escaped)
+			},
+			Expr::BoolLiteral(val) => write!(f, "{}", val),
+			Expr::Nil => write!(f, "nil"),
+			Expr::Keyword(name) => write!(f, ":{}", name),
+			Expr::FnCall{name, args, named} => {
+				write!(f, "({}", name)?;
+				write_list(f, args)?;
+
+				for (key, value) in named {
+					write!(f, " :{} {}", key, value)?;
+				}
+
+				write!(f, ")")
+			},
+			Expr::Args{args, rest} => {
+				write!(f, "(args")?;
+
+				for (i, param) in args.iter().chain(rest).enumerate() {
+					let prefix = if i == args.len() { " &rest" } else { "" };
+
+					match param {
+						(name, Some(r#type)) => write!(f, "{} ({} {})", prefix, name, r#type)?,
+						(name, None) => write!(f, "{} {}", prefix, name)?
+					}
+				}
+
+				write!(f, ")")
+			},
+			Expr::DefineFn{name, args, body, ..} => write!(f, "(fn {} {} {})", name, args, body),
+			Expr::DefineMacro{name, args, body} => write!(f, "(defmacro {} {} {})", name, args, body),
+			Expr::Do{exprs} => {
+				write!(f, "(do")?;
+				write_list(f, exprs)?;
+				write!(f, ")")
+			},
+			Expr::Let{name, r#type} => write!(f, "(let {} {})", name, r#type),
+			Expr::Set{name, value} => write!(f, "(set! {} {})", name, value),
+			Expr::LetStar{bindings, body} => {
+				write!(f, "(let* (")?;
+
+				for (i, Spanned{node: (name, value), ..}) in bindings.iter().enumerate() {
+					write!(f, "{}({} {})", if i > 0 { " " } else { "" }, name, value)?;
+				}
+
+				write!(f, ") {})", body)
+			},
+			Expr::When{cond, body, negated} => {
+				write!(f, "({} {}", if *negated { "unless" } else { "when" }, cond)?;
+				write_list(f, body)?;
+				write!(f, ")")
+			},
+			Expr::While{cond, body} => {
+				write!(f, "(while {}", cond)?;
+				write_list(f, body)?;
+				write!(f, ")")
+			},
+			Expr::Loop{body} => {
+				write!(f, "(loop")?;
+				write_list(f, body)?;
+				write!(f, ")")
+			},
+			Expr::Module{name, body} => {
+				write!(f, "(module {}", name)?;
+				write_list(f, body)?;
+				write!(f, ")")
+			},
+			Expr::Assert{cond} => write!(f, "(assert {})", cond),
+			Expr::AssertEq{left, right} => write!(f, "(assert-eq {} {})", left, right),
+			Expr::And{exprs} => {
+				write!(f, "(and")?;
+				write_list(f, exprs)?;
+				write!(f, ")")
+			},
+			Expr::Or{exprs} => {
+				write!(f, "(or")?;
+				write_list(f, exprs)?;
+				write!(f, ")")
+			},
+			Expr::Quote{expr} => write!(f, "'{}", expr),
+			Expr::Quasiquote{expr} => write!(f, "`{}", expr),
+			Expr::Unquote{expr} => write!(f, ",{}", expr),
+			Expr::UnquoteSplicing{expr} => write!(f, ",@{}", expr)
+		}
+	}
+}
+
+/* Extra context for an error, either pointing at a related position or a free-form hint */
+#[derive(Debug)]
+pub struct Note {
+	pub pos: Option<usize>,
+	pub message: String
+}
+
+impl Note {
+	pub fn new_label(pos: usize, message: String) -> Note {
+		Note {
+			pos: Some(pos),
+			message
+		}
+	}
+
+	pub fn new_hint(message: String) -> Note {
+		Note {
+			pos: None,
+			message
+		}
+	}
+
+	pub fn resolve_all<'a, 'b>(notes: &'b [Note], file: &SourceFile<'a>) -> Vec<(Option<Pos<'a>>, &'b String)> {
+		notes.iter().map(|note| (note.pos.map(|pos| Pos::from_offset(file, pos)), &note.message)).collect()
+	}
+}
+
+#[derive(Debug)]
+pub struct ParseError<'a> {
+	pub message: String,
+	pub code: ErrorCode,
+	pub token: &'a lex::Token,
+	pub notes: Vec<Note>
+}
+
+pub trait Error {
+	fn position<'a>(&self, file: &SourceFile<'a>) -> Pos<'a>;
+	fn message(&self) -> &String;
+
+	/* How many bytes from the position to underline */
+	fn length(&self) -> usize {
+		1
+	}
+
+	/* Secondary positions with a label, and hints without one */
+	fn notes<'a>(&self, _index: &SourceFile<'a>) -> Vec<(Option<Pos<'a>>, &String)> {
+		Vec::new()
+	}
+
+	/* Only lexer and parser errors have a stable code, see `mcf --explain` */
+	fn code(&self) -> Option<ErrorCode> {
+		None
+	}
+}
+
+impl<'a> Error for ParseError<'a> {
+	fn position<'b>(&self, file: &SourceFile<'b>) -> Pos<'b> {
+		Pos::from_line_column(file, self.token.line, self.token.column)
+	}
+
+	fn message(&self) -> &String {
+		&self.message
+	}
+
+	fn length(&self) -> usize {
+		self.token.end - self.token.pos
+	}
+
+	fn code(&self) -> Option<ErrorCode> {
+		Some(self.code)
+	}
+
+	fn notes<'b>(&self, file: &SourceFile<'b>) -> Vec<(Option<Pos<'b>>, &String)> {
+		Note::resolve_all(&self.notes, file)
+	}
+}
+
+impl Error for lex::TokenizeError {
+	fn position<'a>(&self, file: &SourceFile<'a>) -> Pos<'a> {
+		Pos::from_offset(file, self.pos)
+	}
+
+	fn message(&self) -> &String {
+		&self.message
+	}
+
+	fn notes<'a>(&self, file: &SourceFile<'a>) -> Vec<(Option<Pos<'a>>, &String)> {
+		Note::resolve_all(&self.notes, file)
+	}
+
+	fn code(&self) -> Option<ErrorCode> {
+		Some(self.code)
+	}
+}
+
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+pub const RESERVED_WORDS: &[&str] = &["fn", "defmacro", "let", "let*", "set!", "do", "args", "when", "unless", "while", "loop", "module", "assert", "assert-eq", "and", "or", "nil"];
+
+fn check_name<'a>(token: &'a lex::Token, name: &'a str) -> Result<&'a str, ParseError<'a>> {
+	if RESERVED_WORDS.contains(&name) {
+		return Err(ParseError{
+			message: format!("`{}` is a reserved word and cannot be used as a name", name),
+			code: ErrorCode::ReservedName,
+			token,
+			notes: Vec::new()
+		});
+	}
+
+	Ok(name)
+}
+
+type TokenFilter<'a> = Filter<Iter<'a, lex::Token>, fn(&&'a lex::Token) -> bool>;
+
+fn is_not_comment(tok: &&lex::Token) -> bool {
+	!matches!(tok.kind, lex::TokenKind::DocComment(_) | lex::TokenKind::Comment(_))
+}
+
+pub struct Parser<'a> {
+	it: Peekable<TokenFilter<'a>>,
+	docs: HashMap<usize, String>,
+	open_parens: Vec<&'a lex::Token>,
+	quasiquote_depth: usize,
+	/* Inside a plain quote, where names are data */
+	quoted: bool,
+	/* Quote prefixes being parsed, these nest like parentheses do */
+	quote_nesting: usize,
+	max_depth: usize
+}
+
+impl<'a> Parser<'a> {
+	pub fn new_from_tokens(tokens: &'a [lex::Token]) -> Parser<'a> {
+		/* Doc comments are attached to the position of the token following them */
+		let mut docs = HashMap::new();
+		let mut pending: Option<String> = None;
+
+		for tok in tokens {
+			match &tok.kind {
+				lex::TokenKind::DocComment(text) => {
+					pending = Some(match pending.take() {
+						Some(doc) => doc + "\n" + text,
+						None => text.to_string()
+					});
+				},
+				lex::TokenKind::Comment(_) => {},
+				_ => if let Some(doc) = pending.take() {
+					docs.insert(tok.pos, doc);
+				}
+			}
+		}
+
+		Parser {
+			it: tokens.iter().filter(is_not_comment as fn(&&'a lex::Token) -> bool).peekable(),
+			docs,
+			open_parens: Vec::new(),
+			quasiquote_depth: 0,
+			quoted: false,
+			quote_nesting: 0,
+			max_depth: DEFAULT_MAX_DEPTH
+		}
+	}
+
+	pub fn set_max_depth(&mut self, max_depth: usize) {
+		self.max_depth = max_depth;
+	}
+
+	fn check_depth(&self, token: &'a lex::Token) -> Result<(), ParseError<'a>> {
+		if self.open_parens.len() + self.quote_nesting >= self.max_depth {
+			return Err(ParseError{
+				message: "Expression nesting too deep".to_string(),
+				code: ErrorCode::NestingTooDeep,
+				token,
+				notes: Vec::new()
+			});
+		}
+
+		Ok(())
+	}
+
+	/* Points end of input errors at the innermost parenthesis that was never closed */
+	fn unclosed(&self, expected: &str) -> ParseError<'a> {
+		ParseError{
+			message: format!("Unclosed parenthesis, the input ended while expecting {}", expected),
+			code: ErrorCode::Unclosed,
+			token: self.open_parens.last().unwrap(),
+			notes: Vec::new()
+		}
+	}
+
+	/* Counts the expressions up to the closing parenthesis of the current form,
+	 * or returns None if the input ends before it. */
+	fn count_operands(&self) -> Option<usize> {
+		let mut depth = 0;
+		let mut count = 0;
+
+		for tok in self.it.clone() {
+			match tok.kind {
+				lex::TokenKind::LParen => {
+					if depth == 0 {
+						count += 1;
+					}
+
+					depth += 1;
+				},
+				lex::TokenKind::RParen if depth == 0 => return Some(count),
+				lex::TokenKind::RParen => depth -= 1,
+				/* These belong to the expression following them */
+				lex::TokenKind::Quote | lex::TokenKind::Quasiquote | lex::TokenKind::Unquote | lex::TokenKind::UnquoteSplicing => {},
+				_ if depth == 0 => count += 1,
+				_ => {}
+			}
+		}
+
+		None
+	}
+
+	fn at_rparen(&mut self) -> bool {
+		self.it.peek().map_or(false, |tok| tok.kind.is_rparen())
+	}
+
+	fn expect_expr(&mut self, expected: &str) -> Result<Spanned<Expr>, ParseError<'a>> {
+		self.parse_expr()?.ok_or_else(|| self.unclosed(expected))
+	}
+
+	fn parse_fncall(&mut self, name: &str) -> Result<Option<Expr>, ParseError<'a>> {
+		let mut args = Vec::<Spanned<Expr>>::new();
+		let mut named = Vec::<(String, Spanned<Expr>)>::new();
+
+		/* Keywords always start a named argument, and those must come after all positional ones */
+		while let Some(tok) = self.it.peek() {
+			match &tok.kind {
+				lex::TokenKind::RParen => break,
+				lex::TokenKind::Keyword(key) => {
+					let key_tok = self.it.next().unwrap();
+
+					if self.at_rparen() {
+						return Err(ParseError{
+							message: format!("Missing a value for the named argument `:{}`", key),
+							code: ErrorCode::NamedArgument,
+							token: key_tok,
+							notes: Vec::new()
+						});
+					}
+
+					named.push((key.to_string(), self.expect_expr("a value for this named argument")?));
+				},
+				_ if !named.is_empty() => {
+					return Err(ParseError{
+						message: "Positional arguments must come before named arguments".to_string(),
+						code: ErrorCode::NamedArgument,
+						token: tok,
+						notes: Vec::new()
+					});
+				},
+				_ => args.push(self.expect_expr("an argument or a closing parenthesis")?)
+			}
+		}
+
+		Ok(Some(Expr::FnCall{name: name.to_string(), args, named}))
+	}
+
+	fn parse_body(&mut self) -> Result<Vec<Spanned<Expr>>, ParseError<'a>> {
+		let mut exprs = Vec::<Spanned<Expr>>::new();
+
+		while let Some(tok) = self.it.peek() {
+			if tok.kind.is_rparen() {
+				break;
+			}
+
+			exprs.push(self.expect_expr("an expression or a closing parenthesis")?);
+		}
+
+		Ok(exprs)
+	}
+
+	fn parse_do(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
+		Ok(Some(Expr::Do{exprs: self.parse_body()?}))
+	}
+
+	fn parse_when(&mut self, when_token: &'a lex::Token, negated: bool) -> Result<Option<Expr>, ParseError<'a>> {
+		if self.at_rparen() {
+			return Err(ParseError{
+				message: format!("Missing a condition for this `{}`", if negated { "unless" } else { "when" }),
+				code: ErrorCode::MissingOperand,
+				token: when_token,
+				notes: Vec::new()
+			});
+		}
+
+		let cond = Box::new(self.expect_expr("a condition")?);
+		let body = self.parse_body()?;
+
+		Ok(Some(Expr::When{cond, body, negated}))
+	}
+
+	fn parse_while(&mut self, while_token: &'a lex::Token) -> Result<Option<Expr>, ParseError<'a>> {
+		if self.at_rparen() {
+			return Err(ParseError{
+				message: "Missing a condition for this `while`".to_string(),
+				code: ErrorCode::MissingOperand,
+				token: while_token,
+				notes: Vec::new()
+			});
+		}
+
+		let cond = Box::new(self.expect_expr("a condition")?);
+		let body = self.parse_body()?;
+
+		Ok(Some(Expr::While{cond, body}))
+	}
+
+	fn parse_module(&mut self, module_token: &'a lex::Token) -> Result<Option<Expr>, ParseError<'a>> {
+		let name = match self.it.peek().copied() {
+			None => return Err(self.unclosed("a name for this module")),
+			Some(tok) => match &tok.kind {
+				lex::TokenKind::Name(n) => check_name(tok, n)?.to_string(),
+				_ => return Err(ParseError{
+					message: "`module` expects a name followed by its body".to_string(),
+					code: ErrorCode::MissingOperand,
+					token: module_token,
+					notes: Vec::new()
+				})
+			}
+		};
+
+		self.it.next();
+		let body = self.parse_body()?;
+
+		Ok(Some(Expr::Module{name, body}))
+	}
+
+	fn parse_assert_eq(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
+		let left = Box::new(self.expect_expr("a value to compare")?);
+		let right = Box::new(self.expect_expr("a value to compare")?);
+
+		Ok(Some(Expr::AssertEq{left, right}))
+	}
+
+	/* Either a bare name or a `(name type)` pair */
+	fn parse_param(&mut self, tok: &'a lex::Token) -> Result<Param, ParseError<'a>> {
+		match &tok.kind {
+			lex::TokenKind::Name(n) => Ok((check_name(tok, n)?.to_string(), None)),
+			lex::TokenKind::LParen => {
+				/* Stop early at a closing parenthesis, so a short pair like `()` isn't read past */
+				let mut parts = Vec::new();
+
+				let rparen_tok = loop {
+					match self.it.next() {
+						None => {
+							self.open_parens.push(tok);
+							let error = self.unclosed("a closing parenthesis");
+							self.open_parens.pop();
+
+							return Err(error);
+						},
+						Some(next) if next.kind.is_rparen() || parts.len() == 2 => break next,
+						Some(next) => parts.push(next)
+					}
+				};
+
+				match (parts.iter().map(|t| &t.kind).collect::<Vec<_>>().as_slice(), &rparen_tok.kind) {
+					([lex::TokenKind::Name(n), lex::TokenKind::Name(t)], lex::TokenKind::RParen) => {
+						Ok((check_name(parts[0], n)?.to_string(), Some(t.to_string())))
+					},
+					_ => Err(ParseError{
+						message: "Malformed argument, was expecting a name or a `(name type)` pair".to_string(),
+						code: ErrorCode::MalformedArgument,
+						token: tok,
+						notes: Vec::new()
+					})
+				}
+			},
+			_ => Err(ParseError{
+				message: "Unexpected token, was expecting an argument name".to_string(),
+				code: ErrorCode::MalformedArgument,
+				token: tok,
+				notes: Vec::new()
+			})
+		}
+	}
+
+	fn parse_args(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
+		let mut args = Vec::<Param>::new();
+		let mut rest = None;
+
+		while let Some(tok) = self.it.peek() {
+			match &tok.kind {
+				lex::TokenKind::RParen => break,
+				lex::TokenKind::Name(name) if name == "&rest" => {
+					let rest_tok = self.it.next().unwrap();
+
+					let param_tok = match self.it.next() {
+						None => return Err(self.unclosed("a parameter after `&rest`")),
+						Some(tok) if tok.kind.is_rparen() => return Err(ParseError{
+							message: "Missing a parameter after `&rest`".to_string(),
+							code: ErrorCode::MalformedArgument,
+							token: rest_tok,
+							notes: Vec::new()
+						}),
+						Some(tok) => tok
+					};
+
+					rest = Some(self.parse_param(param_tok)?);
+
+					if self.it.peek().map_or(false, |tok| !tok.kind.is_rparen()) {
+						return Err(ParseError{
+							message: "`&rest` must be followed by exactly one final parameter".to_string(),
+							code: ErrorCode::MalformedArgument,
+							token: rest_tok,
+							notes: Vec::new()
+						});
+					}
+				},
+				_ => {
+					let tok = self.it.next().unwrap();
+					args.push(self.parse_param(tok)?);
+				}
+			}
+		}
+
+		Ok(Some(Expr::Args{args, rest}))
+	}
+
+	fn parse_definefn(&mut self, lparen: &'a lex::Token) -> Result<Option<Expr>, ParseError<'a>> {
+		let name_tok = self.it.next();
+
+		let name = match name_tok {
+			None => Err(self.unclosed("a name for this function")),
+			Some(lex::Token{kind: lex::TokenKind::Name(n), ..}) => check_name(name_tok.unwrap(), n),
+			/* TODO: Anonymous functions: */
+			/* Some(lex::Token{kind: lex::TokenKind::LParen, ..}) => ..., */
+			_ => Err(ParseError{
+				message: "Unexpected token, was expecting a name".to_string(),
+				code: ErrorCode::UnexpectedToken,
+				token: name_tok.unwrap(),
+				notes: Vec::new()
+			})
+		}?;
+
+		let args = Box::new(self.expect_expr("an argument list for this function")?);
+		let body = Box::new(self.expect_expr("a body for this function")?);
+
+		let doc = self.docs.remove(&lparen.pos);
+
+		Ok(Some(Expr::DefineFn{name: name.to_string(), args, body, doc}))
+	}
+
+	fn parse_defmacro(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
+		let name_tok = self.it.next();
+
+		let name = match name_tok {
+			None => Err(self.unclosed("a name for this macro")),
+			Some(lex::Token{kind: lex::TokenKind::Name(n), ..}) => check_name(name_tok.unwrap(), n),
+			_ => Err(ParseError{
+				message: "Unexpected token, was expecting a name".to_string(),
+				code: ErrorCode::UnexpectedToken,
+				token: name_tok.unwrap(),
+				notes: Vec::new()
+			})
+		}?;
+
+		let args = Box::new(self.expect_expr("an argument list for this macro")?);
+		let body = Box::new(self.expect_expr("a template for this macro")?);
+
+		Ok(Some(Expr::DefineMacro{name: name.to_string(), args, body}))
+	}
+
+	fn parse_let(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
+		let name_tok = self.it.next();
+
+		let name = match name_tok {
+			None => Err(self.unclosed("a name for this variable")),
+			Some(lex::Token{kind: lex::TokenKind::Name(n), ..}) => check_name(name_tok.unwrap(), n),
+			_ => Err(ParseError{
+				message: "Unexpected token, was expecting a name".to_string(),
+				code: ErrorCode::UnexpectedToken,
+				token: name_tok.unwrap(),
+				notes: Vec::new()
+			})
+		}?;
+
+
+		let type_tok = self.it.next();
+
+		let r#type = match type_tok {
+			None => Err(self.unclosed("a type name for this variable")),
+			Some(lex::Token{kind: lex::TokenKind::Name(n), ..}) => Ok(n),
+			_ => Err(ParseError{
+				message: "Unexpected token, was expecting a type name".to_string(),
+				code: ErrorCode::UnexpectedToken,
+				token: type_tok.unwrap(),
+				notes: Vec::new()
+			})
+		}?;
+
+		Ok(Some(Expr::Let{name: name.to_string(), r#type: r#type.to_string()}))
+	}
+
+	fn parse_set(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
+		let name_tok = self.it.next();
+
+		let name = match name_tok {
+			None => Err(self.unclosed("a name for this assignment")),
+			Some(lex::Token{kind: lex::TokenKind::Name(n), ..}) => check_name(name_tok.unwrap(), n),
+			_ => Err(ParseError{
+				message: "Unexpected token, was expecting a name".to_string(),
+				code: ErrorCode::UnexpectedToken,
+				token: name_tok.unwrap(),
+				notes: Vec::new()
+			})
+		}?;
+
+		let value = Box::new(self.expect_expr("a value for this assignment")?);
+
+		Ok(Some(Expr::Set{name: name.to_string(), value}))
+	}
+
+	fn parse_binding(&mut self, lparen: &'a lex::Token) -> Result<Spanned<Binding>, ParseError<'a>> {
+		let malformed = || ParseError{
+			message: "Malformed binding, was expecting a name followed by a value".to_string(),
+			code: ErrorCode::MalformedBinding,
+			token: lparen,
+			notes: Vec::new()
+		};
+
+		let name_tok = self.it.next();
+
+		let name = match name_tok {
+			None => return Err(self.unclosed("a name for this binding")),
+			Some(lex::Token{kind: lex::TokenKind::Name(n), ..}) => check_name(name_tok.unwrap(), n)?,
+			_ => return Err(malformed())
+		};
+
+		if self.at_rparen() {
+			return Err(malformed());
+		}
+
+		let value = self.expect_expr("a value for this binding")?;
+
+		match self.it.next() {
+			None => Err(self.unclosed("a closing parenthesis")),
+			Some(rparen @ lex::Token{kind: lex::TokenKind::RParen, ..}) => Ok(Spanned::new((name.to_string(), value), lparen.span().to(rparen.span()))),
+			_ => Err(malformed())
+		}
+	}
+
+	fn parse_letstar(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
+		let list_tok = self.it.next();
+
+		match list_tok {
+			None => return Err(self.unclosed("a list of bindings")),
+			Some(lex::Token{kind: lex::TokenKind::LParen, ..}) => {},
+			_ => return Err(ParseError{
+				message: "Unexpected token, was expecting a list of bindings".to_string(),
+				code: ErrorCode::MalformedBinding,
+				token: list_tok.unwrap(),
+				notes: Vec::new()
+			})
+		}
+
+		self.open_parens.push(list_tok.unwrap());
+
+		let mut bindings = Vec::new();
+
+		loop {
+			let tok = self.it.next();
+
+			match tok {
+				None => return Err(self.unclosed("a binding or a closing parenthesis")),
+				Some(lex::Token{kind: lex::TokenKind::RParen, ..}) => break,
+				Some(lparen @ lex::Token{kind: lex::TokenKind::LParen, ..}) => {
+					self.open_parens.push(lparen);
+					bindings.push(self.parse_binding(lparen)?);
+					self.open_parens.pop();
+				},
+				_ => return Err(ParseError{
+					message: "Unexpected token, was expecting a binding".to_string(),
+					code: ErrorCode::MalformedBinding,
+					token: tok.unwrap(),
+					notes: Vec::new()
+				})
+			}
+		}
+
+		self.open_parens.pop();
+
+		let body = Box::new(self.expect_expr("a body for this let*")?);
+
+		Ok(Some(Expr::LetStar{bindings, body}))
+	}
+
+	fn parse_form(&mut self, token: &'a lex::Token) -> Result<Option<Spanned<Expr>>, ParseError<'a>> {
+		if let Some(next) = self.it.next() {
+			let name = match &next.kind {
+				lex::TokenKind::Name(n) => Ok(n),
+				lex::TokenKind::RParen => Err(ParseError{
+					message: "Empty expression `()` is not allowed; expected an operator or special form name".to_string(),
+					code: ErrorCode::EmptyForm,
+					token: next,
+					notes: vec![Note::new_hint("Use `nil` for an empty value".to_string())]
+				}),
+				_ => Err(ParseError{
+					message: "Unexpected token, was expecting a name".to_string(),
+					code: ErrorCode::UnexpectedToken,
+					token: next,
+					notes: Vec::new()
+				})
+			}?;
+
+			let arity = match name.as_str() {
+				"fn" => Some((3, "a name, an argument list and a body")),
+				"defmacro" => Some((3, "a name, an argument list and a template")),
+				"let" => Some((2, "a name and a type")),
+				"let*" => Some((2, "a list of bindings and a body")),
+				"set!" => Some((2, "a name and a value")),
+				"assert" => Some((1, "a condition")),
+				"assert-eq" => Some((2, "two values to compare")),
+				_ => None
+			};
+
+			/* Only look ahead for forms that have a fixed arity, so that long bodies aren't scanned twice.
+			 * Quasiquoted forms may get some of their parts spliced in, so they aren't checked either. */
+			if let Some((expected, description)) = arity.filter(|_| !self.quoted && self.quasiquote_depth == 0) {
+				match self.count_operands() {
+					Some(got) if got != expected => return Err(ParseError{
+						message: format!("`{}` expects {}, but got {} argument{}", name, description, got, if got == 1 { "" } else { "s" }),
+						code: ErrorCode::WrongArity,
+						token: next,
+						notes: Vec::new()
+					}),
+					_ => {}
+				}
+			}
+
+			/* Quoted forms are data, so special forms in them are plain lists */
+			let result = match name.as_str() {
+				_ if self.quoted => self.parse_fncall(name),
+				"fn" => self.parse_definefn(token),
+				"defmacro" => self.parse_defmacro(),
+				"let" => self.parse_let(),
+				"let*" => self.parse_letstar(),
+				"set!" => self.parse_set(),
+				"do" => self.parse_do(),
+				"and" => Ok(Some(Expr::And{exprs: self.parse_body()?})),
+				"or" => Ok(Some(Expr::Or{exprs: self.parse_body()?})),
+				"when" => self.parse_when(next, false),
+				"unless" => self.parse_when(next, true),
+				"while" => self.parse_while(next),
+				"module" => self.parse_module(next),
+				"assert" => Ok(Some(Expr::Assert{cond: Box::new(self.expect_expr("a condition")?)})),
+				"assert-eq" => self.parse_assert_eq(),
+				"loop" => Ok(Some(Expr::Loop{body: self.parse_body()?})),
+				"args" => self.parse_args(),
+				_ => self.parse_fncall(name)
+			}?;
+
+			let rparen_tok = self.it.next();
+
+			match rparen_tok {
+				None => Err(self.unclosed("a closing parenthesis")),
+				Some(rparen @ lex::Token{kind: lex::TokenKind::RParen, ..}) => {
+					Ok(result.map(|e| Spanned::new(e, token.span().to(rparen.span()))))
+				},
+				_ => {
+					Err(ParseError{
+						message: "Unexpected token, was expecting a closing parenthesis".to_string(),
+						code: ErrorCode::UnexpectedToken,
+						token: rparen_tok.unwrap(),
+						notes: vec![Note::new_label(token.pos, "The expression starts here".to_string())]
+					})
+				}
+			}
+		} else {
+			Err(self.unclosed("a name"))
+		}
+	}
+
+	fn parse_quoted(&mut self, token: &'a lex::Token) -> Result<Spanned<Expr>, ParseError<'a>> {
+		let expr = match self.parse_expr()? {
+			Some(expr) => expr,
+			None if !self.open_parens.is_empty() => return Err(self.unclosed("an expression")),
+			None => return Err(ParseError{
+				message: format!("Missing an expression after this {}", token),
+				code: ErrorCode::MissingOperand,
+				token,
+				notes: Vec::new()
+			})
+		};
+
+		Ok(expr)
+	}
+
+	/* Unquotes are rejected at parse time unless they're inside a quasiquote, each
+	 * quasiquote allows one level of unquoting so that nested templates work. */
+	fn parse_quote(&mut self, token: &'a lex::Token) -> Result<Option<Spanned<Expr>>, ParseError<'a>> {
+		let unquote = matches!(token.kind, lex::TokenKind::Unquote | lex::TokenKind::UnquoteSplicing);
+
+		if unquote && self.quasiquote_depth == 0 {
+			return Err(ParseError{
+				message: format!("Unexpected {} outside of a quasiquote", token),
+				code: ErrorCode::UnquoteOutsideQuasiquote,
+				token,
+				notes: Vec::new()
+			});
+		}
+
+		self.check_depth(token)?;
+
+		let (saved_depth, saved_quoted) = (self.quasiquote_depth, self.quoted);
+
+		match token.kind {
+			lex::TokenKind::Quote => self.quoted = true,
+			lex::TokenKind::Quasiquote => self.quasiquote_depth += 1,
+			lex::TokenKind::Unquote | lex::TokenKind::UnquoteSplicing => {
+				self.quasiquote_depth -= 1;
+				self.quoted = false;
+			},
+			_ => {}
+		}
+
+		self.quote_nesting += 1;
+		let result = self.parse_quoted(token);
+		self.quote_nesting -= 1;
+		self.quasiquote_depth = saved_depth;
+		self.quoted = saved_quoted;
+
+		let expr = Box::new(result?);
+		let span = token.span().to(expr.span);
+
+		let node = match token.kind {
+			lex::TokenKind::Quote => Expr::Quote{expr},
+			lex::TokenKind::Quasiquote => Expr::Quasiquote{expr},
+			lex::TokenKind::Unquote => Expr::Unquote{expr},
+			lex::TokenKind::UnquoteSplicing => Expr::UnquoteSplicing{expr},
+			_ => unreachable!()
+		};
+
+		Ok(Some(Spanned::new(node, span)))
+	}
+
+	pub fn parse_expr(&mut self) -> Result<Option<Spanned<Expr>>, ParseError<'a>> {
+		if let Some(token) = self.it.next() {
+			match &token.kind {
+				lex::TokenKind::LParen => {
+					self.check_depth(token)?;
+
+					self.open_parens.push(token);
+					let result = self.parse_form(token);
+					self.open_parens.pop();
+
+					result
+				},
+
+				lex::TokenKind::Quote | lex::TokenKind::Quasiquote | lex::TokenKind::Unquote | lex::TokenKind::UnquoteSplicing => {
+					self.parse_quote(token)
+				},
+
+				lex::TokenKind::Name(name) => {
+					let node = match name.as_str() {
+						"nil" => Expr::Nil,
+						/* Quoted special form names are just symbols */
+						_ if RESERVED_WORDS.contains(&name.as_str()) && !self.quoted && self.quasiquote_depth == 0 => {
+							return Err(ParseError{
+								message: format!("`{}` is a special form and must be used in a call like `({} ...)`", name, name),
+								code: ErrorCode::BareSpecialForm,
+								token,
+								notes: Vec::new()
+							});
+						},
+						_ => Expr::VariableRef{var: name.to_string()}
+					};
+
+					Ok(Some(Spanned::new(node, token.span())))
+				},
+
+				lex::TokenKind::Integer(val, radix) => {
+					Ok(Some(Spanned::new(Expr::IntegerLiteral(*val, *radix), token.span())))
+				},
+
+				#[cfg(feature = "bigint")]
+				lex::TokenKind::BigInteger(val) => {
+					Ok(Some(Spanned::new(Expr::BigIntegerLiteral(val.clone()), token.span())))
+				},
+
+				lex::TokenKind::Float(val) => {
+					Ok(Some(Spanned::new(Expr::FloatLiteral(*val), token.span())))
+				},
+
+				lex::TokenKind::Bool(val) => {
+					Ok(Some(Spanned::new(Expr::BoolLiteral(*val), token.span())))
+				},
+
+				lex::TokenKind::String(val) => {
+					Ok(Some(Spanned::new(Expr::StringLiteral(val.to_string()), token.span())))
+				},
+
+				lex::TokenKind::Keyword(name) => {
+					Ok(Some(Spanned::new(Expr::Keyword(name.to_string()), token.span())))
+				},
+
+				lex::TokenKind::RBracket if !self.open_parens.is_empty() => {
+					let open = self.open_parens.last().unwrap();
+
+					Err(ParseError{
+						message: format!("Unexpected {}, was expecting a closing parenthesis", token),
+						code: ErrorCode::UnexpectedToken,
+						token,
+						notes: vec![Note::new_label(open.pos, "The expression starts here".to_string())]
+					})
+				},
+
+				lex::TokenKind::RParen if self.open_parens.is_empty() => {
+					Err(ParseError{
+						message: "Unexpected closing parenthesis; there is no matching opening parenthesis".to_string(),
+						code: ErrorCode::UnmatchedClosingParen,
+						token,
+						notes: Vec::new()
+					})
+				},
+
+				_ => {
+					Err(ParseError{
+						message: format!("Unexpected {}", token),
+						code: ErrorCode::UnexpectedToken,
+						token,
+						notes: Vec::new()
+					})
+				}
+			}
+		} else {
+			Ok(None)
+		}
+	}
+
+	pub fn parse_program(&mut self) -> Result<Vec<Spanned<Expr>>, ParseError<'a>> {
+		let mut exprs = Vec::new();
+
+		while let Some(expr) = self.parse_expr()? {
+			exprs.push(expr);
+		}
+
+		Ok(exprs)
+	}
+
+	/* Skips the rest of a top-level expression that failed to parse, given the
+	 * tokens from its start. Stops early at a `(` at the start of a line, since
+	 * that is most likely the next top-level form after a missing `)`. */
+	fn synchronize(&mut self, start: Peekable<TokenFilter<'a>>) {
+		self.it = start;
+		let mut depth = 0;
+
+		while let Some(tok) = self.it.peek() {
+			match tok.kind {
+				lex::TokenKind::LParen if depth > 0 && tok.column == 1 => break,
+				lex::TokenKind::LParen => depth += 1,
+				lex::TokenKind::RParen if depth > 0 => depth -= 1,
+				/* These belong to the expression following them */
+				lex::TokenKind::Quote | lex::TokenKind::Quasiquote | lex::TokenKind::Unquote | lex::TokenKind::UnquoteSplicing => {
+					self.it.next();
+					continue;
+				},
+				_ => {}
+			}
+
+			self.it.next();
+
+			if depth == 0 {
+				break;
+			}
+		}
+	}
+
+	/* Like parse_program, but carries on after an error with the next top-level
+	 * expression, returning everything that parsed along with every error */
+	pub fn parse_program_recovering(&mut self) -> (Vec<Spanned<Expr>>, Vec<ParseError<'a>>) {
+		let mut exprs = Vec::new();
+		let mut errors = Vec::new();
+
+		loop {
+			let start = self.it.clone();
+
+			match self.parse_expr() {
+				Ok(Some(expr)) => exprs.push(expr),
+				Ok(None) => break,
+				Err(e) => {
+					errors.push(e);
+					self.synchronize(start);
+				}
+			}
+		}
+
+		(exprs, errors)
+	}
+}
@@ -1,287 +1,745 @@
+use std::env;
 use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
-use std::iter::Peekable;
-use std::slice::Iter;
+use mcf::span::{Span, Spanned};
+use mcf::diagnostic::{self, Severity};
+use mcf::{check, code, dot, expand, fold, lex, util, Error, Expr, Parser, Pos, SourceFile};
 
-mod lex;
-mod util;
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+	Human,
+	Json
+}
 
-struct Pos<'a> {
-	line: usize,
-	column: usize,
+#[derive(Clone, Copy, PartialEq)]
+enum Emit {
+	Tokens,
+	Ast,
+	Dot,
+	None
+}
 
-	line_content: &'a str
+#[derive(Clone, Copy)]
+struct Options {
+	format: Format,
+	color: bool,
+	emit: Emit,
+	fold: bool,
+	warnings: Warnings,
+	time: bool,
+	max_errors: usize
 }
 
-impl<'a> Pos<'a> {
-	fn from_offset(source: &'a str, pos: usize) -> Pos<'a> {
-		let mut line: usize = 1;
-		let mut column: usize = 1;
+enum Command {
+	Explain(code::ErrorCode),
+	Repl,
+	Compile{paths: Vec<String>, eval: Option<String>, output: Option<String>, options: Options, watch: bool}
+}
 
-		for (idx, ch) in source.char_indices() {
-			if idx == pos {
-				break;
-			}
+/* Which optional checks to run, and whether their warnings fail the build */
+#[derive(Clone, Copy)]
+struct Warnings {
+	unused: bool,
+	misspelled_forms: bool,
+	deny: bool
+}
 
-			column += 1;
-			if ch == '\n' {
-				column = 1;
-				line += 1;
-			}
+impl Warnings {
+	/* For `-W<name>` and `-Wno-<name>`, including `all`, false if there's no such warning */
+	fn set(&mut self, name: &str, enabled: bool) -> bool {
+		match name {
+			"unused" => self.unused = enabled,
+			"misspelled-forms" => self.misspelled_forms = enabled,
+			"all" => {
+				self.unused = enabled;
+				self.misspelled_forms = enabled;
+			},
+			_ => return false
 		}
 
-		Pos { line, column, line_content: source.lines().nth(line - 1).unwrap() }
+		true
 	}
 }
 
-#[derive(Debug)]
-enum Expr {
-	VariableRef{var: String},
-	IntegerLiteral(i64),
-	StringLiteral(String),
-	FnCall{name: String, args: Vec<Expr>},
-	Args{args: Vec<Expr>},
-	DefineFn{name: String, args: Box<Expr>, body: Box<Expr>},
-	Do{exprs: Vec<Expr>},
-	Let{name: String, r#type: String},
+impl Default for Warnings {
+	fn default() -> Warnings {
+		Warnings {
+			unused: false,
+			/* Off by default, builtins like `len` or `get` are too close to special form names */
+			misspelled_forms: false,
+			deny: false
+		}
+	}
 }
 
-#[derive(Debug)]
-struct ParseError<'a> {
-	message: String,
-	token: &'a lex::Token
+#[derive(Default)]
+struct Timings {
+	lex: Duration,
+	parse: Duration
 }
 
-trait Error {
-	fn position<'a>(&self, source: &'a str) -> Pos<'a>;
-	fn message(&self) -> &String;
+/* Everything loading a program needs besides the file itself */
+struct Session {
+	options: Options,
+	diagnostics: Diagnostics,
+	timings: Timings,
+	/* Where the requested dumps go, stdout unless `-o` was given */
+	out: Box<dyn Write>,
+	/* The first failed write, nothing more is written after it */
+	write_error: Option<io::Error>
 }
 
-impl<'a> Error for ParseError<'a> {
-	fn position<'b>(&self, source: &'b str) -> Pos<'b> {
-		Pos::from_offset(source, self.token.pos)
+impl Session {
+	fn write(&mut self, text: &str) {
+		if self.write_error.is_none() {
+			self.write_error = self.out.write_all(text.as_bytes()).err();
+		}
 	}
+}
 
-	fn message(&self) -> &String {
-		&self.message
-	}
+struct IncludeError {
+	message: String,
+	span: Span
 }
 
-impl Error for lex::TokenizeError {
-	fn position<'a>(&self, source: &'a str) -> Pos<'a> {
-		Pos::from_offset(source, self.pos)
+impl Error for IncludeError {
+	fn position<'a>(&self, file: &SourceFile<'a>) -> Pos<'a> {
+		Pos::from_offset(file, self.span.start)
 	}
 
 	fn message(&self) -> &String {
 		&self.message
 	}
+
+	fn length(&self) -> usize {
+		self.span.end - self.span.start
+	}
 }
 
-fn report_error<T: Error>(source: &str, error: &T) -> ! {
-	let pos = error.position(source);
-	println!("Error at {}:{}: {}", pos.line, pos.column, error.message());
-	println!(" {} | {}", pos.line, pos.line_content);
-	println!(" {} | {}~", pos.line, util::Fill::with(pos.column - 1, ' '));
-	process::exit(1);
+const DEFAULT_MAX_ERRORS: usize = 20;
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+struct Diagnostic {
+	severity: Severity,
+	file: String,
+	line: usize,
+	column: usize,
+	offset: usize,
+	length: usize,
+	code: Option<code::ErrorCode>,
+	message: String,
+	/* The line and column of each note, if it has a position */
+	notes: Vec<(Option<(usize, usize)>, String)>
 }
 
-struct Parser<'a> {
-	it: Peekable<Iter<'a, lex::Token>>
+/* Collects the diagnostics of every file making up the program */
+struct Diagnostics {
+	format: Format,
+	color: bool,
+	/* Errors past this many are only counted, 0 means no limit */
+	max_errors: usize,
+	/* Report warnings as errors, failing the build */
+	deny_warnings: bool,
+	errors: usize,
+	reported: Vec<Diagnostic>
 }
 
-impl<'a> Parser<'a> {
-	fn new_from_tokens(tokens: &'a [lex::Token]) -> Parser<'a> {
-		Parser {
-			it: tokens.iter().peekable()
+impl Diagnostics {
+	fn new(format: Format, color: bool, max_errors: usize, deny_warnings: bool) -> Diagnostics {
+		Diagnostics {
+			format,
+			color,
+			max_errors,
+			deny_warnings,
+			errors: 0,
+			reported: Vec::new()
 		}
 	}
 
-	fn parse_fncall(&mut self, name: &str) -> Result<Option<Expr>, ParseError<'a>> {
-		let mut args = Vec::<Expr>::new();
+	fn push<T: Error>(&mut self, severity: Severity, name: &str, file: &SourceFile, error: &T) {
+		if severity == Severity::Error {
+			self.errors += 1;
 
-		while let Some(tok) = self.it.peek() {
-			if matches!(tok.kind, lex::TokenKind::RParen) {
-				break;
+			if self.max_errors != 0 && self.errors > self.max_errors {
+				return;
 			}
+		}
+
+		let pos = error.position(file);
+		let notes = error.notes(file);
 
-			args.push(self.parse_expr()?.unwrap());
+		if self.format == Format::Human {
+			eprint!("{}", diagnostic::format_error(severity, name, file, error, self.color));
 		}
 
-		Ok(Some(Expr::FnCall{name: name.to_string(), args}))
+		self.reported.push(Diagnostic{
+			severity,
+			file: name.to_string(),
+			line: pos.line,
+			column: pos.column,
+			offset: pos.offset,
+			length: error.length(),
+			code: error.code(),
+			message: error.message().clone(),
+			notes: notes.into_iter().map(|(pos, message)| (pos.map(|pos| (pos.line, pos.column)), message.clone())).collect()
+		});
 	}
 
-	fn parse_do(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
-		let mut exprs = Vec::<Expr>::new();
+	fn report<T: Error>(&mut self, name: &str, file: &SourceFile, error: &T) {
+		self.push(Severity::Error, name, file, error);
+	}
 
-		while let Some(tok) = self.it.peek() {
-			if matches!(tok.kind, lex::TokenKind::RParen) {
-				break;
+	/* Reports the error of a failed result, leaving it to the caller to stop */
+	fn check<T, E: Error>(&mut self, name: &str, file: &SourceFile, result: Result<T, E>) -> Option<T> {
+		result.map_err(|e| self.report(name, file, &e)).ok()
+	}
+
+	fn warn<T: Error>(&mut self, name: &str, file: &SourceFile, error: &T) {
+		let severity = if self.deny_warnings { Severity::Error } else { Severity::Warning };
+		self.push(severity, name, file, error);
+	}
+
+	fn flush(&self) {
+		let suppressed = self.errors.saturating_sub(self.max_errors);
+
+		if self.format != Format::Json {
+			if self.max_errors != 0 && suppressed > 0 {
+				eprintln!("... and {} more error{}", suppressed, if suppressed == 1 { "" } else { "s" });
 			}
 
-			exprs.push(self.parse_expr()?.unwrap());
+			return;
 		}
 
-		Ok(Some(Expr::Do{exprs}))
+		let entries: Vec<String> = self.reported.iter().map(|d| {
+			let notes: Vec<String> = d.notes.iter().map(|(pos, message)| match pos {
+				Some((line, column)) => format!("{{\"line\":{},\"column\":{},\"message\":{}}}", line, column, util::JsonString(message)),
+				None => format!("{{\"line\":null,\"column\":null,\"message\":{}}}", util::JsonString(message))
+			}).collect();
+
+			let code = d.code.map_or("null".to_string(), |code| format!("\"{}\"", code));
+
+			format!("{{\"severity\":\"{}\",\"code\":{},\"file\":{},\"line\":{},\"column\":{},\"offset\":{},\"length\":{},\"message\":{},\"notes\":[{}]}}",
+				d.severity.as_str(), code, util::JsonString(&d.file), d.line, d.column, d.offset, d.length, util::JsonString(&d.message), notes.join(","))
+		}).collect();
+
+		println!("[{}]", entries.join(","));
 	}
+}
 
-	fn parse_args(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
-		let mut args = Vec::<Expr>::new();
+fn nesting_depth(tokens: &[lex::Token]) -> i64 {
+	tokens.iter().fold(0, |depth, tok| match tok.kind {
+		lex::TokenKind::LParen => depth + 1,
+		lex::TokenKind::RParen => depth - 1,
+		_ => depth
+	})
+}
 
-		while let Some(tok) = self.it.peek() {
-			if matches!(tok.kind, lex::TokenKind::RParen) {
+/* Returns false if the input is incomplete and more lines should be read. */
+fn run_repl_input(source: &str) -> bool {
+	let file = SourceFile::new_from_source(source);
+	let mut diagnostics = Diagnostics::new(Format::Human, util::use_color(), 0, false);
+
+	let tokens = match lex::Tokenizer::new_from_source(source).tokenize() {
+		Ok(tokens) => tokens,
+		/* A string continued with a `\` at the end of the line */
+		Err(e) if e.code == code::ErrorCode::UnterminatedString => return false,
+		Err(e) => {
+			diagnostics.report("<repl>", &file, &e);
+			return true;
+		}
+	};
+
+	if nesting_depth(&tokens) > 0 {
+		return false;
+	}
+
+	let mut parser = Parser::new_from_tokens(&tokens);
+
+	loop {
+		match parser.parse_expr() {
+			Ok(Some(e)) => println!("{}", e),
+			Ok(None) => break,
+			Err(e) => {
+				diagnostics.report("<repl>", &file, &e);
 				break;
 			}
-
-			args.push(self.parse_expr()?.unwrap());
 		}
+	}
 
-		Ok(Some(Expr::Args{args}))
-	}
-
-	fn parse_definefn(&mut self, fn_token: &'a lex::Token) -> Result<Option<Expr>, ParseError<'a>> {
-		let name_tok = self.it.next();
-
-		let name = match name_tok {
-			None => Err(ParseError{
-				message: "Unexpected end of input, was a name for this function".to_string(),
-				token: fn_token
-			}),
-			Some(lex::Token{kind: lex::TokenKind::Name(n), pos: _}) => Ok(n),
-			/* TODO: Anonymous functions: */
-			/* Some(lex::Token{kind: lex::TokenKind::LParen, pos: _}) => ..., */
-			_ => Err(ParseError{
-				message: "Unexpected token, was expecting a name".to_string(),
-				token: name_tok.unwrap()
-			})
-		}?;
-
-		let args = Box::new(self.parse_expr()?.unwrap());
-		let body = Box::new(self.parse_expr()?.unwrap());
-
-		Ok(Some(Expr::DefineFn{name: name.to_string(), args, body}))
-	}
-
-	fn parse_let(&mut self, let_token: &'a lex::Token) -> Result<Option<Expr>, ParseError<'a>> {
-		let name_tok = self.it.next();
-
-		let name = match name_tok {
-			None => Err(ParseError{
-				message: "Unexpected end of input, was a name for this variable".to_string(),
-				token: let_token
-			}),
-			Some(lex::Token{kind: lex::TokenKind::Name(n), pos: _}) => Ok(n),
-			_ => Err(ParseError{
-				message: "Unexpected token, was expecting a name".to_string(),
-				token: name_tok.unwrap()
-			})
-		}?;
-
-
-		let type_tok = self.it.next();
-
-		let r#type = match type_tok {
-			None => Err(ParseError{
-				message: "Unexpected end of input, was a type name for this variable".to_string(),
-				token: let_token
-			}),
-			Some(lex::Token{kind: lex::TokenKind::Name(n), pos: _}) => Ok(n),
-			_ => Err(ParseError{
-				message: "Unexpected token, was expecting a type name".to_string(),
-				token: type_tok.unwrap()
-			})
-		}?;
-
-		Ok(Some(Expr::Let{name: name.to_string(), r#type: r#type.to_string()}))
-	}
-
-	fn parse_expr(&mut self) -> Result<Option<Expr>, ParseError<'a>> {
-		if let Some(token) = self.it.next() {
-			match &token.kind {
-				lex::TokenKind::LParen => {
-					if let Some(next) = self.it.next() {
-						let name = match &next.kind {
-							lex::TokenKind::Name(n) => Ok(n),
-							_ => Err(ParseError{
-								message: "Unexpected token, was expecting a name".to_string(),
-								token: next
-							})
-						}?;
-
-						let result = match name.as_str() {
-							"fn" => self.parse_definefn(next),
-							"let" => self.parse_let(next),
-							"do" => self.parse_do(),
-							"args" => self.parse_args(),
-							_ => self.parse_fncall(name)
-						}?;
-
-						let rparen_tok = self.it.next();
-
-						match rparen_tok {
-							None => Err(ParseError{
-								message: "Unexpected end of input, was expecting a closing parenthesis to close this expression".to_string(),
-								token
-							}),
-							Some(lex::Token{kind: lex::TokenKind::RParen, pos: _}) => {
-								Ok(result)
-							},
-							_ => {
-								Err(ParseError{
-									message: "Unexpected token, was expecting a closing parenthesis".to_string(),
-									token: rparen_tok.unwrap()
-								})
-							}
-						}
-					} else {
-						Err(ParseError{
-							message: "Unexpected end of file, was expecting a name".to_string(),
-							token
-						})
-					}
-				},
+	true
+}
 
-				lex::TokenKind::Name(name) => {
-					Ok(Some(Expr::VariableRef{var: name.to_string()}))
-				},
+fn run_repl() {
+	let stdin = io::stdin();
+	let mut buffer = String::new();
 
-				lex::TokenKind::Integer(val) => {
-					Ok(Some(Expr::IntegerLiteral(*val)))
-				},
+	loop {
+		print!("{}", if buffer.is_empty() { "mcf> " } else { "...> " });
+		io::stdout().flush().unwrap();
 
-				lex::TokenKind::String(val) => {
-					Ok(Some(Expr::StringLiteral(val.to_string())))
-				},
+		let mut line = String::new();
 
-				_ => {
-					Err(ParseError{
-						message: format!("Unexpeced {}", token),
-						token
-					})
-				}
+		match stdin.lock().read_line(&mut line) {
+			Ok(0) => {
+				println!();
+				break;
+			},
+			Ok(_) => {},
+			/* The offending line has been consumed, so carry on with the next one */
+			Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+				eprintln!("Cannot read input: {}", err);
+				continue;
+			},
+			Err(err) => {
+				eprintln!("Cannot read input: {}", err);
+				break;
 			}
+		}
+
+		buffer.push_str(&line);
+
+		if run_repl_input(&buffer) {
+			buffer.clear();
+		}
+	}
+}
+
+/* Reads a file, or stdin if there's no path */
+fn read_input(path: Option<&Path>) -> io::Result<String> {
+	match path {
+		Some(path) => fs::read_to_string(path),
+		None => {
+			let mut contents = String::new();
+			io::stdin().read_to_string(&mut contents)?;
+			Ok(contents)
+		}
+	}
+}
+
+/* Returns the `path` of a top-level `(include "path")` form, or None for anything else */
+fn include_path(expr: &Spanned<Expr>) -> Option<Result<&str, IncludeError>> {
+	match &expr.node {
+		Expr::FnCall{name, args, named} if name == "include" => match (args.as_slice(), named.is_empty()) {
+			([Spanned{node: Expr::StringLiteral(path), ..}], true) => Some(Ok(path)),
+			_ => Some(Err(IncludeError{
+				message: "`include` expects a single path string".to_string(),
+				span: expr.span
+			}))
+		},
+		_ => None
+	}
+}
+
+/* Parses a single file, splicing in the top-level expressions of the files it
+ * includes. Relative include paths are resolved against the directory of the
+ * including file, and `including` holds the files currently being loaded so
+ * that cycles are caught instead of recursing forever. Returns None if any
+ * errors were reported. */
+fn load_program(name: &str, contents: &str, path: &Path, including: &mut Vec<PathBuf>, session: &mut Session) -> Option<Vec<Spanned<Expr>>> {
+	let options = session.options;
+	let file = SourceFile::new_from_source(contents);
+
+	let start = Instant::now();
+	let mut tokenizer = lex::Tokenizer::new_from_source(contents);
+	let (tokens, errors) = tokenizer.tokenize_partial();
+	session.timings.lex += start.elapsed();
+
+	/* Show what was read even if tokenizing failed part way */
+	if options.emit == Emit::Tokens {
+		session.write(&format!("Tokens: {:#?}\n", tokens));
+	}
+
+	for e in &errors {
+		session.diagnostics.report(name, &file, e);
+	}
+
+	/* Parsing what's left would mostly report follow-on errors */
+	if !errors.is_empty() {
+		return None;
+	}
+
+	let start = Instant::now();
+	let (parsed, errors) = Parser::new_from_tokens(&tokens).parse_program_recovering();
+	session.timings.parse += start.elapsed();
+
+	for e in &errors {
+		session.diagnostics.report(name, &file, e);
+	}
+
+	if !errors.is_empty() {
+		return None;
+	}
+
+	/* Macros are only visible within the file defining them */
+	let parsed = session.diagnostics.check(name, &file, expand::expand_macros(parsed))?;
+
+	let mut file_exprs = Vec::new();
+
+	for e in parsed {
+		if include_path(&e).is_some() {
+			file_exprs.push(e);
+			continue;
+		}
+
+		let e = if options.fold {
+			session.diagnostics.check(name, &file, fold::fold(e))?
 		} else {
-			Ok(None)
+			e
+		};
+
+		if options.emit == Emit::Ast {
+			session.write(&format!("Expr: {:#?}\n", e));
+		}
+
+		file_exprs.push(e);
+	}
+
+	/* Spans only make sense within their own file, so this can't see across includes yet */
+	session.diagnostics.check(name, &file, check::check_duplicate_fns(&file_exprs, &file))?;
+
+	if options.warnings.misspelled_forms {
+		for warning in check::check_misspelled_forms(&file_exprs) {
+			session.diagnostics.warn(name, &file, &warning);
 		}
 	}
+
+	if options.warnings.unused {
+		for warning in check::check_unused_bindings(&file_exprs) {
+			session.diagnostics.warn(name, &file, &warning);
+		}
+	}
+
+	let mut exprs = Vec::new();
+
+	for e in file_exprs {
+		let include = match include_path(&e) {
+			Some(include) => session.diagnostics.check(name, &file, include)?,
+			None => {
+				exprs.push(e);
+				continue;
+			}
+		};
+
+		let target = path.parent().unwrap_or_else(|| Path::new("")).join(include);
+		let canonical = fs::canonicalize(&target).unwrap_or_else(|_| target.clone());
+
+		if including.contains(&canonical) {
+			session.diagnostics.report(name, &file, &IncludeError{
+				message: format!("Including `{}` here would create an include cycle", include),
+				span: e.span
+			});
+			return None;
+		}
+
+		let included = fs::read_to_string(&target).map_err(|err| IncludeError{
+			message: format!("Cannot read `{}`: {}", target.display(), err),
+			span: e.span
+		});
+		let included = session.diagnostics.check(name, &file, included)?;
+
+		including.push(canonical);
+		let result = load_program(&target.display().to_string(), &included, &target, including, session);
+		including.pop();
+
+		exprs.extend(result?);
+	}
+
+	Some(exprs)
 }
 
-fn main() {
-	let contents = fs::read_to_string("test").unwrap();
+/* Loads every input and writes out what was asked for, returning false if
+ * anything went wrong. */
+fn compile(paths: &[String], eval: Option<&str>, output: Option<&str>, options: Options) -> bool {
+	let out: Box<dyn Write> = match output {
+		Some(path) => match fs::File::create(path) {
+			Ok(file) => Box::new(file),
+			Err(err) => {
+				eprintln!("Cannot create `{}`: {}", path, err);
+				return false;
+			}
+		},
+		None => Box::new(io::stdout())
+	};
+
+	let mut session = Session{
+		options,
+		diagnostics: Diagnostics::new(options.format, options.color, options.max_errors, options.warnings.deny),
+		timings: Timings::default(),
+		out,
+		write_error: None
+	};
+	let mut exprs = Vec::new();
+	let mut failed = false;
+
+	/* Each input is reported on separately, an error in one doesn't stop the others */
+	let mut inputs = Vec::new();
+
+	if let Some(source) = eval {
+		inputs.push(("<eval>".to_string(), source.to_string(), None));
+	}
 
-	let mut tokenizer = lex::Tokenizer::new_from_source(&contents);
-	let tokens = tokenizer.tokenize().unwrap_or_else(|e| report_error(&contents, &e));
+	for path in paths {
+		let (name, path) = if path == "-" {
+			("<stdin>".to_string(), None)
+		} else {
+			(path.clone(), Some(PathBuf::from(path)))
+		};
+
+		match read_input(path.as_deref()) {
+			Ok(contents) => inputs.push((name, contents, path)),
+			Err(err) => {
+				eprintln!("Cannot read `{}`: {}", name, err);
+				return false;
+			}
+		}
+	}
 
-	println!("Tokens: {:#?}", tokens);
+	for (name, contents, path) in &inputs {
+		let mut including: Vec<PathBuf> = path.iter().map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.clone())).collect();
 
-	let mut parser = Parser::new_from_tokens(&tokens);
+		/* Includes from stdin or `--eval` are relative to the working directory */
+		match load_program(name, contents, path.as_deref().unwrap_or_else(|| Path::new(".")), &mut including, &mut session) {
+			Some(file_exprs) => exprs.extend(file_exprs),
+			None => failed = true
+		}
+	}
+
+	if options.time {
+		let timings = &session.timings;
+		eprintln!("lex: {:.3}ms, parse: {:.3}ms", timings.lex.as_secs_f64() * 1000.0, timings.parse.as_secs_f64() * 1000.0);
+	}
+
+	/* Denied warnings don't stop loading, but still fail the build */
+	let failed = failed || session.diagnostics.errors > 0;
+
+	if !failed && options.emit == Emit::Dot {
+		session.write(&dot::to_dot(&exprs));
+	}
 
+	session.diagnostics.flush();
+
+	if let Some(err) = session.write_error {
+		eprintln!("Cannot write output: {}", err);
+		return false;
+	}
+
+	!failed
+}
+
+fn modified_times(paths: &[String]) -> Vec<Option<SystemTime>> {
+	paths.iter().map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok()).collect()
+}
+
+/* Recompiles whenever one of the input files changes. This polls the
+ * modification times, and doesn't notice changes to included files yet. */
+fn watch_inputs(paths: &[String], eval: Option<&str>, output: Option<&str>, options: Options) -> ! {
 	loop {
-		let expr = parser.parse_expr().unwrap_or_else(|e| report_error(&contents, &e));
+		let last = modified_times(paths);
+
+		compile(paths, eval, output, options);
+		eprintln!("Watching for changes...");
+
+		while modified_times(paths) == last {
+			thread::sleep(WATCH_INTERVAL);
+		}
+	}
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+	let mut format = Format::Human;
+	let mut color = None;
+	let mut emit = None;
+	let mut paths = Vec::new();
+	let mut repl = false;
+	let mut fold = false;
+	let mut check = false;
+	let mut warnings = Warnings::default();
+	let mut time = false;
+	let mut max_errors = DEFAULT_MAX_ERRORS;
+	let mut output = None;
+	let mut eval = None;
+	let mut watch = false;
+
+	while let Some(arg) = args.next() {
+		/* Values can be given either as `--flag value` or `--flag=value` */
+		let (arg, mut inline) = match arg.split_once('=') {
+			Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+			_ => (arg, None)
+		};
+
+		let takes_value = matches!(arg.as_str(), "--format" | "--error-format" | "--color" | "--emit" | "--max-errors" | "--output" | "--eval" | "--explain" | "--deny");
+
+		if inline.is_some() && !takes_value {
+			return Err(format!("`{}` doesn't take a value", arg));
+		}
+
+		let mut value = || inline.take().or_else(|| args.next());
 
-		match expr {
-			Some(e) => println!("Expr: {:#?}", e),
-			None => { break; }
+		match arg.as_str() {
+			"--format" | "--error-format" => {
+				format = match value().as_deref() {
+					Some("human") => Format::Human,
+					Some("json") => Format::Json,
+					_ => {
+						return Err(format!("{} expects either `human` or `json`", arg));
+					}
+				};
+			},
+			"--color" => {
+				color = match value().as_deref() {
+					Some("always") => Some(true),
+					Some("never") => Some(false),
+					Some("auto") => None,
+					_ => {
+						return Err("--color expects one of `always`, `never` or `auto`".to_string());
+					}
+				};
+			},
+			"--emit" => {
+				emit = match value().as_deref() {
+					Some("tokens") => Some(Emit::Tokens),
+					Some("ast") => Some(Emit::Ast),
+					Some("dot") => Some(Emit::Dot),
+					Some("none") => Some(Emit::None),
+					_ => {
+						return Err("--emit expects one of `tokens`, `ast`, `dot` or `none`".to_string());
+					}
+				};
+			},
+			"--fold" => {
+				fold = true;
+			},
+			"--check" => {
+				check = true;
+			},
+			"--warn-unused" => {
+				warnings.unused = true;
+			},
+			"--deny" => {
+				match value().as_deref() {
+					Some("warnings") => warnings.deny = true,
+					_ => return Err("--deny expects `warnings`".to_string())
+				}
+			},
+			_ if arg.starts_with("-W") => {
+				let (name, enabled) = match arg[2..].strip_prefix("no-") {
+					Some(name) => (name, false),
+					None => (&arg[2..], true)
+				};
+
+				if !warnings.set(name, enabled) {
+					return Err(format!("Unknown warning `{}`", arg));
+				}
+			},
+			"--time" => {
+				time = true;
+			},
+			"--max-errors" => {
+				max_errors = match value().map(|n| n.parse()) {
+					Some(Ok(n)) => n,
+					_ => {
+						return Err("--max-errors expects a number, or 0 for no limit".to_string());
+					}
+				};
+			},
+			"-o" | "--output" => {
+				output = match value() {
+					Some(path) => Some(path),
+					None => {
+						return Err(format!("{} expects a path to write the output to", arg));
+					}
+				};
+			},
+			"--eval" => {
+				eval = match value() {
+					Some(source) => Some(source),
+					None => {
+						return Err("--eval expects a program to run".to_string());
+					}
+				};
+			},
+			"--explain" => {
+				return match value() {
+					Some(name) => match code::ErrorCode::lookup(&name) {
+						Some(code) => Ok(Command::Explain(code)),
+						None => Err(format!("`{}` is not a known error code", name))
+					},
+					None => Err("--explain expects an error code, like `E0101`".to_string())
+				};
+			},
+			"--watch" => {
+				watch = true;
+			},
+			"--repl" => {
+				repl = true;
+			},
+			_ if arg == "-" || !arg.starts_with('-') => {
+				paths.push(arg);
+			},
+			_ => {
+				return Err(format!("Unknown argument `{}`", arg));
+			}
+		}
+	}
+
+	/* Keep machine-readable output free of dumps unless explicitly asked for */
+	let emit = match emit {
+		_ if check => Emit::None,
+		Some(emit) => emit,
+		None if format == Format::Json => Emit::None,
+		None => Emit::Ast
+	};
+
+	/* Checking only reports diagnostics, so don't create or truncate an output file either */
+	let output = if check { None } else { output };
+
+	/* Without a path, read a program piped in and only start the REPL for a terminal */
+	if paths.is_empty() && eval.is_none() && !repl && !util::stdin_is_tty() {
+		paths.push("-".to_string());
+	}
+
+	if (paths.is_empty() && eval.is_none()) || repl {
+		return Ok(Command::Repl);
+	}
+
+	if watch && (paths.is_empty() || paths.iter().any(|path| path == "-")) {
+		return Err("--watch needs input files to watch, it can't watch stdin".to_string());
+	}
+
+	let options = Options{format, color: color.unwrap_or_else(util::use_color), emit, fold, warnings, time, max_errors};
+
+	Ok(Command::Compile{paths, eval, output, options, watch})
+}
+
+/* The exit status is decided here alone: 2 for bad arguments, 1 if compiling failed */
+fn main() {
+	let command = match parse_args(env::args().skip(1)) {
+		Ok(command) => command,
+		Err(message) => {
+			eprintln!("{}", message);
+			process::exit(2);
 		}
+	};
+
+	let success = match command {
+		Command::Explain(code) => {
+			println!("{}\n\n{}", code, code.explanation());
+			true
+		},
+		Command::Repl => {
+			run_repl();
+			true
+		},
+		Command::Compile{paths, eval, output, options, watch: true} => watch_inputs(&paths, eval.as_deref(), output.as_deref(), options),
+		Command::Compile{paths, eval, output, options, watch: false} => compile(&paths, eval.as_deref(), output.as_deref(), options)
+	};
+
+	if !success {
+		process::exit(1);
 	}
 }
@@ -0,0 +1,41 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize
+}
+
+impl Span {
+	pub fn new(start: usize, end: usize) -> Span {
+		Span {
+			start,
+			end
+		}
+	}
+
+	pub fn to(self, other: Span) -> Span {
+		Span::new(self.start, other.end)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+	pub node: T,
+	pub span: Span
+}
+
+impl<T> Spanned<T> {
+	pub fn new(node: T, span: Span) -> Spanned<T> {
+		Spanned {
+			node,
+			span
+		}
+	}
+}
+
+impl<T: fmt::Display> fmt::Display for Spanned<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.node.fmt(f)
+	}
+}
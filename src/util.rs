@@ -1,3 +1,4 @@
+use std::env;
 use std::fmt;
 
 pub struct Fill {
@@ -16,9 +17,135 @@ impl Fill {
 
 impl fmt::Display for Fill {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		for _ in 0..self.len {
-			write!(f, "{}", self.ch)?;
+		f.write_str(&self.ch.to_string().repeat(self.len))
+	}
+}
+
+#[derive(Clone, Copy)]
+pub enum Style {
+	Red,
+	Yellow,
+	Dim
+}
+
+impl Style {
+	fn code(self) -> &'static str {
+		match self {
+			Style::Red => "1;31",
+			Style::Yellow => "1;33",
+			Style::Dim => "2"
+		}
+	}
+}
+
+pub struct Paint<T> {
+	value: T,
+	style: Style,
+	enabled: bool
+}
+
+impl<T: fmt::Display> Paint<T> {
+	pub fn with(value: T, style: Style, enabled: bool) -> Paint<T> {
+		Paint {
+			value,
+			style,
+			enabled
+		}
+	}
+}
+
+impl<T: fmt::Display> fmt::Display for Paint<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.enabled {
+			write!(f, "\x1b[{}m{}\x1b[0m", self.style.code(), self.value)
+		} else {
+			write!(f, "{}", self.value)
+		}
+	}
+}
+
+/* None when there's no way to tell on this platform */
+#[cfg(unix)]
+fn is_tty(fd: i32) -> Option<bool> {
+	extern "C" {
+		fn isatty(fd: i32) -> i32;
+	}
+
+	Some(unsafe { isatty(fd) == 1 })
+}
+
+#[cfg(not(unix))]
+fn is_tty(_fd: i32) -> Option<bool> {
+	None
+}
+
+/* Assumes an interactive session if it can't tell */
+pub fn stdin_is_tty() -> bool {
+	is_tty(0).unwrap_or(true)
+}
+
+/* For diagnostics, which are written to stderr */
+pub fn use_color() -> bool {
+	let no_color = env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty());
+
+	!no_color && is_tty(2).unwrap_or(false)
+}
+
+/* Levenshtein distance, counting a swap of two adjacent characters as a single edit */
+pub fn edit_distance(a: &str, b: &str) -> usize {
+	let a: Vec<char> = a.chars().collect();
+	let b: Vec<char> = b.chars().collect();
+
+	let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+
+	for i in 1..=a.len() {
+		let mut row = vec![i; b.len() + 1];
+
+		for j in 1..=b.len() {
+			let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+			row[j] = (rows[i - 1][j] + 1).min(row[j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+
+			if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+				row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+			}
+		}
+
+		rows.push(row);
+	}
+
+	rows[a.len()][b.len()]
+}
+
+/* How many terminal columns a character takes up, an approximation of the
+ * East Asian Width property that covers combining marks, CJK and emoji */
+pub fn char_width(ch: char) -> usize {
+	match ch as u32 {
+		0x0300..=0x036F | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F => 0,
+		0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF
+			| 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60
+			| 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F680..=0x1F6FF | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+		_ => 1
+	}
+}
+
+pub struct JsonString<'a>(pub &'a str);
+
+impl fmt::Display for JsonString<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "\"")?;
+
+		for ch in self.0.chars() {
+			match ch {
+				'"' => write!(f, "\\\"")?,
+				'\\' => write!(f, "\\\\")?,
+				'\n' => write!(f, "\\n")?,
+				'\r' => write!(f, "\\r")?,
+				'\t' => write!(f, "\\t")?,
+				c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+				c => write!(f, "{}", c)?
+			}
 		}
-		Ok(())
+
+		write!(f, "\"")
 	}
 }
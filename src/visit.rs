@@ -0,0 +1,158 @@
+use crate::span::{Span, Spanned};
+use crate::Expr;
+
+/* Every hook defaults to walking into the children of the expression,
+ * so passes only need to override the ones they're interested in. */
+pub trait Visitor<'ast> {
+	fn visit_expr(&mut self, expr: &'ast Spanned<Expr>) {
+		match expr.node {
+			Expr::VariableRef{..} => self.visit_variable_ref(expr),
+			Expr::IntegerLiteral(..) | Expr::FloatLiteral(_) | Expr::StringLiteral(_) | Expr::BoolLiteral(_) | Expr::Nil | Expr::Keyword(_) => self.visit_literal(expr),
+			#[cfg(feature = "bigint")]
+			Expr::BigIntegerLiteral(_) => self.visit_literal(expr),
+			Expr::FnCall{..} => self.visit_fncall(expr),
+			Expr::Args{..} => self.visit_args(expr),
+			Expr::DefineFn{..} => self.visit_definefn(expr),
+			Expr::DefineMacro{..} => self.visit_defmacro(expr),
+			Expr::Do{..} => self.visit_do(expr),
+			Expr::Let{..} => self.visit_let(expr),
+			Expr::Set{..} => self.visit_set(expr),
+			Expr::LetStar{..} => self.visit_letstar(expr),
+			Expr::When{..} => self.visit_when(expr),
+			Expr::While{..} => self.visit_while(expr),
+			Expr::Loop{..} => self.visit_loop(expr),
+			Expr::Module{..} => self.visit_module(expr),
+			Expr::Assert{..} | Expr::AssertEq{..} => self.visit_assert(expr),
+			Expr::And{..} => self.visit_and(expr),
+			Expr::Or{..} => self.visit_or(expr),
+			Expr::Quote{..} | Expr::Quasiquote{..} => self.visit_quote(expr),
+			Expr::Unquote{..} | Expr::UnquoteSplicing{..} => self.visit_unquote(expr)
+		}
+	}
+
+	fn visit_variable_ref(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_literal(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_fncall(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_args(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_definefn(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_defmacro(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_do(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_let(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_set(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_letstar(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_when(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_while(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_loop(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_module(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_assert(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_and(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_or(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_quote(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+
+	fn visit_unquote(&mut self, expr: &'ast Spanned<Expr>) {
+		walk_expr(self, expr)
+	}
+}
+
+pub fn walk_expr<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Spanned<Expr>) {
+	match &expr.node {
+		Expr::VariableRef{..} | Expr::IntegerLiteral(..) | Expr::FloatLiteral(_) | Expr::StringLiteral(_) | Expr::BoolLiteral(_) | Expr::Nil | Expr::Keyword(_) | Expr::Let{..} | Expr::Args{..} => {},
+		#[cfg(feature = "bigint")]
+		Expr::BigIntegerLiteral(_) => {},
+		Expr::FnCall{args, named, ..} => {
+			args.iter().for_each(|arg| visitor.visit_expr(arg));
+			named.iter().for_each(|(_, value)| visitor.visit_expr(value));
+		},
+		Expr::DefineFn{args, body, ..} | Expr::DefineMacro{args, body, ..} => {
+			visitor.visit_expr(args);
+			visitor.visit_expr(body);
+		},
+		Expr::Do{exprs} | Expr::Loop{body: exprs} | Expr::Module{body: exprs, ..} | Expr::And{exprs} | Expr::Or{exprs} => exprs.iter().for_each(|expr| visitor.visit_expr(expr)),
+		Expr::Set{value, ..} | Expr::Assert{cond: value} => visitor.visit_expr(value),
+		Expr::AssertEq{left, right} => {
+			visitor.visit_expr(left);
+			visitor.visit_expr(right);
+		},
+		Expr::Quote{expr} | Expr::Quasiquote{expr} | Expr::Unquote{expr} | Expr::UnquoteSplicing{expr} => visitor.visit_expr(expr),
+		Expr::LetStar{bindings, body} => {
+			bindings.iter().for_each(|binding| visitor.visit_expr(&binding.node.1));
+			visitor.visit_expr(body);
+		},
+		Expr::When{cond, body, ..} | Expr::While{cond, body} => {
+			visitor.visit_expr(cond);
+			body.iter().for_each(|expr| visitor.visit_expr(expr));
+		}
+	}
+}
+
+#[derive(Default)]
+pub struct VariableRefCollector<'ast> {
+	pub refs: Vec<(&'ast str, Span)>
+}
+
+impl<'ast> Visitor<'ast> for VariableRefCollector<'ast> {
+	fn visit_variable_ref(&mut self, expr: &'ast Spanned<Expr>) {
+		if let Expr::VariableRef{var} = &expr.node {
+			self.refs.push((var, expr.span));
+		}
+	}
+}
+
+pub fn collect_variable_refs(expr: &Spanned<Expr>) -> Vec<(&str, Span)> {
+	let mut collector = VariableRefCollector::default();
+	collector.visit_expr(expr);
+	collector.refs
+}
@@ -0,0 +1,30 @@
+use mcf::check::check_unused_bindings;
+use mcf::lex::Tokenizer;
+use mcf::Parser;
+
+/* The warnings for a source, as their message and the text they point at */
+fn unused(source: &str) -> Vec<(String, &str)> {
+	let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+	let exprs = Parser::new_from_tokens(&tokens).parse_program().unwrap();
+
+	check_unused_bindings(&exprs).into_iter().map(|w| (w.message, &source[w.span.start..w.span.end])).collect()
+}
+
+#[test]
+fn unused_bindings() {
+	assert_eq!(unused("(do (let x int) (print 1))"), [("Variable `x` is never used".to_string(), "(let x int)")]);
+	assert_eq!(unused("(let* ((a 1) (b 2)) b)"), [("Variable `a` is never used".to_string(), "(a 1)")]);
+
+	/* Shadowed before any use */
+	assert_eq!(unused("(do (let x int) (let x int) (print x))"), [("Variable `x` is never used".to_string(), "(let x int)")]);
+}
+
+#[test]
+fn used_bindings() {
+	assert!(unused("(do (let x int) (print x))").is_empty());
+	assert!(unused("(when c (let x int) (set! y x))").is_empty());
+	assert!(unused("(let* ((a 1) (b a)) b)").is_empty());
+
+	/* Top-level bindings may be used elsewhere */
+	assert!(unused("(let x int)").is_empty());
+}
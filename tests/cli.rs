@@ -0,0 +1,182 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::io::Write;
+use std::process::{self, Command, Output, Stdio};
+
+/* Runs the compiler binary with the given arguments, without color or a terminal */
+fn mcf(args: &[&str]) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_mcf")).args(args).arg("--color=never").output().unwrap()
+}
+
+/* A file from the golden test cases */
+fn case(name: &str) -> String {
+	format!("{}/tests/cases/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn stdout(output: &Output) -> String {
+	String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+	String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn warning_flags() {
+	let source = "(lte x int)";
+
+	/* Misspelled forms are only checked for when asked to */
+	let output = mcf(&["--check", "--eval", source]);
+	assert!(output.status.success());
+	assert_eq!(stderr(&output), "");
+
+	for flags in &[&["-Wmisspelled-forms"][..], &["-Wall"], &["-Wno-all", "-Wmisspelled-forms"]] {
+		let output = mcf(&[*flags, &["--check", "--eval", source]].concat());
+		assert!(output.status.success());
+		assert!(stderr(&output).contains("warning"), "{:?} didn't warn", flags);
+	}
+
+	for flags in &[&["-Wall", "-Wno-all"][..], &["-Wall", "-Wno-misspelled-forms"]] {
+		let output = mcf(&[*flags, &["--check", "--eval", source]].concat());
+		assert!(output.status.success());
+		assert_eq!(stderr(&output), "", "{:?} still warned", flags);
+	}
+
+	/* Builtins that happen to be close to a special form don't fail a build by default */
+	let output = mcf(&["--check", "--deny", "warnings", "--eval", "(print (len xs) (get m k) (dot a b))"]);
+	assert!(output.status.success(), "{}", stderr(&output));
+
+	let output = mcf(&["--check", "-Wall", "--deny", "warnings", "--eval", "(let* ((a 1)) 2)"]);
+	assert_eq!(output.status.code(), Some(1));
+
+	let output = mcf(&["-Wno-such-thing", "--eval", "1"]);
+	assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn check() {
+	let output = mcf(&["--check", &case("example.mcf")]);
+	assert_eq!(output.status.code(), Some(0));
+	assert_eq!(stdout(&output), "");
+	assert_eq!(stderr(&output), "");
+
+	let output = mcf(&["--check", &case("unclosed.mcf")]);
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(stdout(&output), "");
+	assert!(stderr(&output).contains("error[E0102]"), "{}", stderr(&output));
+
+	let output = mcf(&["--check", "--format", "json", &case("example.mcf")]);
+	assert_eq!(output.status.code(), Some(0));
+	assert_eq!(stdout(&output), "[]\n");
+
+	let output = mcf(&["--check", "--format", "json", &case("unclosed.mcf")]);
+	assert_eq!(output.status.code(), Some(1));
+	assert!(stdout(&output).starts_with("[{\"severity\":\"error\",\"code\":\"E0102\","), "{}", stdout(&output));
+}
+
+#[test]
+fn time() {
+	let output = mcf(&["--check", "--time", &case("example.mcf")]);
+	assert!(output.status.success());
+
+	/* Something like `lex: 0.123ms, parse: 0.456ms` */
+	let timings = stderr(&output);
+	let line = timings.trim_end();
+	let (lex, parse) = line.split_once(", ").unwrap();
+
+	for (part, label) in &[(lex, "lex: "), (parse, "parse: ")] {
+		let ms = part.strip_prefix(label).and_then(|ms| ms.strip_suffix("ms")).unwrap_or_else(|| panic!("Unexpected timing line {}", line));
+		assert!(ms.parse::<f64>().is_ok(), "{}", line);
+	}
+
+	/* Off by default */
+	assert_eq!(stderr(&mcf(&["--check", &case("example.mcf")])), "");
+}
+
+#[test]
+fn partial_token_dump() {
+	let output = mcf(&["--emit", "tokens", "--eval", "(print 1 \"abc"]);
+	assert_eq!(output.status.code(), Some(1));
+
+	let stdout = stdout(&output);
+	assert!(stdout.starts_with("Tokens: ["), "{}", stdout);
+	assert!(stdout.contains("\"print\""), "{}", stdout);
+	assert!(stderr(&output).contains("error[E0002]"), "{}", stderr(&output));
+}
+
+#[test]
+fn max_errors() {
+	let source = "(print a\\b)\n".repeat(30);
+	let run = |args: &[&str]| {
+		let output = mcf(&[args, &["--check", "--eval", &source]].concat());
+		assert_eq!(output.status.code(), Some(1));
+
+		let stderr = stderr(&output);
+		(stderr.matches("error[E0006]").count(), stderr.lines().last().unwrap_or("").to_string())
+	};
+
+	assert_eq!(run(&[]), (20, "... and 10 more errors".to_string()));
+	assert_eq!(run(&["--max-errors", "3"]), (3, "... and 27 more errors".to_string()));
+	assert_eq!(run(&["--max-errors=29"]), (29, "... and 1 more error".to_string()));
+	assert_eq!(run(&["--max-errors", "30"]).0, 30);
+	assert!(!run(&["--max-errors", "30"]).1.starts_with("..."));
+	assert_eq!(run(&["--max-errors", "0"]).0, 30);
+
+	/* The cap also applies to JSON output, just without the tail */
+	let output = mcf(&["--check", "--format", "json", "--max-errors", "3", "--eval", &source]);
+	assert_eq!(stdout(&output).matches("\"severity\"").count(), 3);
+}
+
+/* A fresh directory for a test to write its input files to */
+fn temp_dir(name: &str) -> PathBuf {
+	let dir = env::temp_dir().join(format!("mcf-{}-{}", name, process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+#[test]
+fn include() {
+	let dir = temp_dir("include");
+	fs::create_dir(dir.join("lib")).unwrap();
+	fs::write(dir.join("main.mcf"), "(include \"lib/a.mcf\")\n(print (a))\n").unwrap();
+	fs::write(dir.join("lib/a.mcf"), "(include \"b.mcf\")\n(fn a (args) (b))\n").unwrap();
+	fs::write(dir.join("lib/b.mcf"), "(fn b (args) 1)\n").unwrap();
+
+	/* Included expressions are spliced in where the include was, with paths relative to the including file */
+	let output = mcf(&["--emit", "dot", dir.join("main.mcf").to_str().unwrap()]);
+	assert!(output.status.success(), "{}", stderr(&output));
+
+	let stdout = stdout(&output);
+	let labels: Vec<_> = stdout.lines().filter_map(|line| line.split_once("[label=\"")).map(|(_, label)| label.trim_end_matches("\"];")).collect();
+	assert_eq!(labels, ["DefineFn b", "Args", "IntegerLiteral 1", "DefineFn a", "Args", "FnCall b", "FnCall print", "FnCall a"]);
+
+	fs::write(dir.join("missing.mcf"), "(include \"nowhere.mcf\")\n").unwrap();
+	let output = mcf(&["--check", dir.join("missing.mcf").to_str().unwrap()]);
+	assert_eq!(output.status.code(), Some(1));
+	assert!(stderr(&output).contains("missing.mcf:1:1: error: Cannot read"), "{}", stderr(&output));
+
+	fs::write(dir.join("one.mcf"), "(include \"two.mcf\")\n").unwrap();
+	fs::write(dir.join("two.mcf"), "(print 2)\n(include \"one.mcf\")\n").unwrap();
+	let output = mcf(&["--check", dir.join("one.mcf").to_str().unwrap()]);
+	assert_eq!(output.status.code(), Some(1));
+	assert!(stderr(&output).contains("two.mcf:2:1: error: Including `one.mcf` here would create an include cycle"), "{}", stderr(&output));
+
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn repl() {
+	let mut child = Command::new(env!("CARGO_BIN_EXE_mcf")).args(&["--repl", "--color=never"])
+		.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+		.spawn().unwrap();
+
+	/* A line that isn't UTF-8 is reported and skipped, and a continued string waits for the next line */
+	child.stdin.take().unwrap().write_all(b"\xff\n(print 1)\n(print \"ab\\\n  cd\")\n").unwrap();
+	let output = child.wait_with_output().unwrap();
+
+	assert!(output.status.success());
+	assert_eq!(stdout(&output), "mcf> mcf> (print 1)\nmcf> ...> (print \"abcd\")\nmcf> \n");
+	assert_eq!(stderr(&output), "Cannot read input: stream did not contain valid UTF-8\n");
+}
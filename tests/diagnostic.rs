@@ -0,0 +1,20 @@
+use mcf::diagnostic::{format_error, Severity};
+use mcf::lex::Tokenizer;
+use mcf::{Parser, SourceFile};
+
+#[test]
+fn formatted_error() {
+	let source = "(print 1)\n(let x)\n";
+	let file = SourceFile::new_from_source(source);
+
+	let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+	let error = Parser::new_from_tokens(&tokens).parse_program().unwrap_err();
+
+	assert_eq!(format_error(Severity::Error, "test.mcf", &file, &error, false), "\
+test.mcf:2:2: error[E0109]: `let` expects a name and a type, but got 1 argument
+ 2 | (let x)
+ 2 |  ~~~
+");
+
+	assert!(format_error(Severity::Warning, "test.mcf", &file, &error, false).starts_with("test.mcf:2:2: warning[E0109]: "));
+}
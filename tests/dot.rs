@@ -0,0 +1,58 @@
+use mcf::dot::to_dot;
+use mcf::lex::Tokenizer;
+use mcf::Parser;
+
+fn dot(source: &str) -> String {
+	let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+	to_dot(&Parser::new_from_tokens(&tokens).parse_program().unwrap())
+}
+
+/* Braces outside of quoted labels, which have to balance out for the graph to parse */
+fn brace_depths(out: &str) -> Vec<i32> {
+	let mut depths = Vec::new();
+	let mut depth = 0;
+	let mut chars = out.chars();
+	let mut quoted = false;
+
+	while let Some(ch) = chars.next() {
+		match ch {
+			'\\' if quoted => {
+				chars.next();
+			},
+			'"' => quoted = !quoted,
+			'{' if !quoted => depth += 1,
+			'}' if !quoted => depth -= 1,
+			_ => continue
+		}
+
+		depths.push(depth);
+	}
+
+	assert!(!quoted, "Unterminated label in {}", out);
+	depths
+}
+
+#[test]
+fn well_formed() {
+	let out = dot("(fn f (args a (b int) &rest c) (print \"}{\\\"\" a :key 'x))\n(let* ((x 1.5)) (when x nil))");
+
+	assert!(out.starts_with("digraph ast {\n"), "{}", out);
+	assert!(out.ends_with("}\n"), "{}", out);
+
+	let depths = brace_depths(&out);
+	assert!(depths.iter().all(|&depth| depth >= 0), "{}", out);
+	assert_eq!(depths.last(), Some(&0), "{}", out);
+
+	/* Every edge connects two declared nodes */
+	let nodes: Vec<&str> = out.lines().filter_map(|line| line.trim().split_once(" [label=").map(|(id, _)| id)).collect();
+
+	for line in out.lines().filter(|line| line.contains("->")) {
+		let (from, to) = line.trim().trim_end_matches(';').split_once(" -> ").unwrap();
+		assert!(nodes.contains(&from) && nodes.contains(&to), "{}", line);
+	}
+}
+
+#[test]
+fn empty_program() {
+	assert_eq!(dot(""), "digraph ast {\n}\n");
+}
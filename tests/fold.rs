@@ -0,0 +1,64 @@
+use mcf::fold::fold;
+use mcf::lex::Tokenizer;
+use mcf::Parser;
+
+/* Folds a single expression, returning it printed or the error message */
+fn run(source: &str) -> String {
+	let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+	let expr = Parser::new_from_tokens(&tokens).parse_expr().unwrap().unwrap();
+
+	match fold(expr) {
+		Ok(expr) => expr.to_string(),
+		Err(e) => e.message
+	}
+}
+
+#[test]
+fn arithmetic() {
+	assert_eq!(run("(+ 1 2 3)"), "6");
+	assert_eq!(run("(+)"), "0");
+	assert_eq!(run("(- 5)"), "-5");
+	assert_eq!(run("(- 10 3 2)"), "5");
+	assert_eq!(run("(* 2 3 4)"), "24");
+	assert_eq!(run("(/ 20 2 5)"), "2");
+	assert_eq!(run("(/ -7 2)"), "-3");
+	assert_eq!(run("(mod 7 3)"), "1");
+	assert_eq!(run("(mod -7 3)"), "-1");
+}
+
+#[test]
+fn comparisons() {
+	assert_eq!(run("(< 1 2)"), "true");
+	assert_eq!(run("(< 2 1)"), "false");
+	assert_eq!(run("(> 2 1)"), "true");
+	assert_eq!(run("(> 1 1)"), "false");
+	assert_eq!(run("(= 3 3)"), "true");
+	assert_eq!(run("(= 3 4)"), "false");
+}
+
+#[test]
+fn nested() {
+	assert_eq!(run("(print (+ 1 (* 2 3)) x)"), "(print 7 x)");
+	assert_eq!(run("(< (+ 1 1) (- 5 1))"), "true");
+	assert_eq!(run("(+ 1 x)"), "(+ 1 x)");
+}
+
+#[test]
+fn errors() {
+	assert_eq!(run("(/ 1 0)"), "Division by zero while folding `/`");
+	assert_eq!(run("(mod 1 0)"), "Division by zero while folding `mod`");
+	assert_eq!(run("(/ -9223372036854775808 -1)"), "Integer overflow while folding `/`");
+	assert_eq!(run("(mod -9223372036854775808 -1)"), "Integer overflow while folding `mod`");
+	assert_eq!(run("(+ 9223372036854775807 1)"), "Integer overflow while folding `+`");
+	assert_eq!(run("(- -9223372036854775808)"), "Integer overflow while folding `-`");
+}
+
+#[test]
+fn untouched() {
+	assert_eq!(run("(+ 1 (+ 2 3))"), "6");
+	assert_eq!(run("(+ 1 (f 2))"), "(+ 1 (f 2))");
+	assert_eq!(run("(+ 1 :by 2)"), "(+ 1 :by 2)");
+	assert_eq!(run("(+ 1 2.5)"), "(+ 1 2.5)");
+	assert_eq!(run("(print '(+ 1 2))"), "(print '(+ 1 2))");
+	assert_eq!(run("(fn f (args x) (do (+ x 1) (* 2 3)))"), "(fn f (args x) (do (+ x 1) 6))");
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use mcf::diagnostic::{self, Severity};
+use mcf::{check, expand, lex, Parser, SourceFile};
+
+/* Runs a source through the frontend, returning the parsed expressions one per
+ * line followed by any warnings, or the diagnostics for the first failing stage
+ * exactly as they would be printed. */
+fn run(name: &str, source: &str) -> String {
+	let file = SourceFile::new_from_source(source);
+
+	let (tokens, errors) = lex::Tokenizer::new_from_source(source).tokenize_partial();
+
+	if !errors.is_empty() {
+		return errors.iter().map(|e| diagnostic::format_error(Severity::Error, name, &file, e, false)).collect();
+	}
+
+	let (exprs, errors) = Parser::new_from_tokens(&tokens).parse_program_recovering();
+
+	if !errors.is_empty() {
+		return errors.iter().map(|e| diagnostic::format_error(Severity::Error, name, &file, e, false)).collect();
+	}
+
+	let exprs = match expand::expand_macros(exprs) {
+		Ok(exprs) => exprs,
+		Err(e) => return diagnostic::format_error(Severity::Error, name, &file, &e, false)
+	};
+
+	if let Err(e) = check::check_duplicate_fns(&exprs, &file) {
+		return diagnostic::format_error(Severity::Error, name, &file, &e, false);
+	}
+
+	/* The misspelled-forms check is opt-in, but run for every case here */
+	let warnings = check::check_misspelled_forms(&exprs);
+
+	exprs.iter().map(|expr| format!("{}\n", expr))
+		.chain(warnings.iter().map(|w| diagnostic::format_error(Severity::Warning, name, &file, w, false)))
+		.collect()
+}
+
+/* Set MCF_BLESS=1 to write the current output to the .expected files instead */
+#[test]
+fn golden() {
+	let bless = env::var_os("MCF_BLESS").is_some();
+	let cases = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("cases");
+
+	let mut paths: Vec<_> = fs::read_dir(&cases).unwrap()
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension().map_or(false, |ext| ext == "mcf"))
+		.collect();
+	paths.sort();
+
+	assert!(!paths.is_empty(), "No test cases found in {}", cases.display());
+
+	let mut failures = Vec::new();
+
+	for path in &paths {
+		let name = path.file_name().unwrap().to_str().unwrap();
+		let source = fs::read_to_string(path).unwrap();
+		let actual = run(name, &source);
+		let expected_path = path.with_extension("expected");
+
+		if bless {
+			fs::write(&expected_path, &actual).unwrap();
+			continue;
+		}
+
+		let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+
+		if actual != expected {
+			failures.push(format!("{}:\n--- expected\n{}--- actual\n{}", name, expected, actual));
+		}
+	}
+
+	assert!(failures.is_empty(), "{} of {} cases failed\n\n{}", failures.len(), paths.len(), failures.join("\n"));
+}
@@ -0,0 +1,207 @@
+use mcf::code::ErrorCode;
+use mcf::lex::{TokenKind, Tokenizer, TokenizerOptions};
+use mcf::{Parser, SourceFile};
+
+fn kinds(source: &str) -> Vec<String> {
+	Tokenizer::new_from_source(source).tokenize().unwrap().iter().map(|tok| format!("{:?}", tok.kind)).collect()
+}
+
+fn error_code(source: &str) -> ErrorCode {
+	Tokenizer::new_from_source(source).tokenize().unwrap_err().code
+}
+
+#[test]
+fn names_and_numbers() {
+	assert_eq!(kinds("- + -a +b a.b a:b x' café"), [
+		"Name(\"-\")", "Name(\"+\")", "Name(\"-a\")", "Name(\"+b\")", "Name(\"a.b\")", "Name(\"a:b\")", "Name(\"x'\")", "Name(\"café\")"
+	]);
+
+	assert_eq!(kinds("1 -1 +1 0x1F 0b10 1.5 -1e3"), [
+		"Integer(1, Decimal)", "Integer(-1, Decimal)", "Integer(1, Decimal)", "Integer(31, Hexadecimal)", "Integer(2, Binary)", "Float(1.5)", "Float(-1000.0)"
+	]);
+
+	assert_eq!(kinds("."), ["Dot"]);
+}
+
+#[test]
+fn malformed_tokens() {
+	assert_eq!(error_code(".."), ErrorCode::InvalidName);
+	assert_eq!(error_code("\\"), ErrorCode::InvalidCharacter);
+	assert_eq!(error_code("a\\b"), ErrorCode::InvalidCharacter);
+	assert_eq!(error_code("1a"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code("-1a"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code(".5"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code("-.5"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code("0x1.0"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code("0b1.1"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code("0x"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code("0b"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code("0o"), ErrorCode::InvalidDigit);
+	assert_eq!(error_code("0x)"), ErrorCode::InvalidDigit);
+}
+
+#[test]
+fn semicolon_comments() {
+	let options = TokenizerOptions{keep_comments: true, ..TokenizerOptions::default()}.with_comment_char(';').unwrap();
+	let tokens = Tokenizer::new_from_source_with_options(";; a\n(f) ; b", options).tokenize().unwrap();
+	let kinds: Vec<_> = tokens.iter().map(|tok| format!("{:?}", tok.kind)).collect();
+
+	/* With ';' as the comment character, ';;' is a plain comment */
+	assert_eq!(kinds, [
+		"Comment(\"; a\")", "LParen", "Name(\"f\")", "RParen", "Comment(\" b\")"
+	]);
+}
+
+#[test]
+fn invalid_comment_chars() {
+	for &ch in &['1', '(', ')', '[', ']', '"', '\'', ' ', '`', ',', '-', '+', 'b', 'é', '٣'] {
+		assert!(TokenizerOptions::default().with_comment_char(ch).is_err(), "`{}` was accepted", ch);
+	}
+
+	for &ch in &[';', '#'] {
+		assert!(TokenizerOptions::default().with_comment_char(ch).is_ok(), "`{}` was rejected", ch);
+	}
+}
+
+#[test]
+fn crlf_line_endings() {
+	let source = "(a b)\r\n(c \"d\\\r\ne\")\r\n# f\r\ng\r\n";
+	let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+	let kinds: Vec<_> = tokens.iter().map(|tok| format!("{:?}", tok.kind)).collect();
+
+	/* Carriage returns never end up in a token, a continued string included */
+	assert_eq!(kinds, [
+		"LParen", "Name(\"a\")", "Name(\"b\")", "RParen", "LParen", "Name(\"c\")", "String(\"de\")", "RParen", "Name(\"g\")"
+	]);
+
+	let positions: Vec<_> = tokens.iter().map(|tok| (tok.line, tok.column)).collect();
+	assert_eq!(positions, [(1, 1), (1, 2), (1, 4), (1, 5), (2, 1), (2, 2), (2, 4), (3, 3), (5, 1)]);
+
+	let file = SourceFile::new_from_source(source);
+	assert_eq!(file.line_column(tokens[8].pos), (5, 1));
+	assert_eq!(file.line_content(2), "(c \"d\\");
+}
+
+#[test]
+fn shebang() {
+	/* Skipped regardless of the comment character, without shifting what follows */
+	let options = TokenizerOptions::default().with_comment_char(';').unwrap();
+	let tokens = Tokenizer::new_from_source_with_options("#!/usr/bin/env mcf\n  (print 1)", options).tokenize().unwrap();
+
+	assert_eq!(format!("{:?}", tokens[0].kind), "LParen");
+	assert_eq!((tokens[0].pos, tokens[0].line, tokens[0].column), (21, 2, 3));
+
+	/* Only at the very start of the source */
+	let error = Tokenizer::new_from_source_with_options(" #!/usr/bin/env mcf", options).tokenize().unwrap_err();
+	assert_eq!(error.code, ErrorCode::InvalidCharacter);
+}
+
+#[test]
+fn token_iterator() {
+	let mut it = Tokenizer::new_from_source("a b 0b2 c");
+
+	/* Tokens are read one at a time, and iteration stops after the first error */
+	assert_eq!(format!("{:?}", it.next().unwrap().unwrap().kind), "Name(\"a\")");
+	assert_eq!(format!("{:?}", it.next().unwrap().unwrap().kind), "Name(\"b\")");
+	assert_eq!(it.next().unwrap().unwrap_err().code, ErrorCode::InvalidDigit);
+	assert!(it.next().is_none());
+	assert!(it.next().is_none());
+
+	let mut it = Tokenizer::new_from_source("a");
+	assert!(it.next().unwrap().is_ok());
+	assert!(it.next().is_none());
+	assert!(it.next().is_none());
+
+	/* While tokenize_partial carries on after errors */
+	let (tokens, errors) = Tokenizer::new_from_source("a 0b2 \"b\\q\" c").tokenize_partial();
+	assert_eq!(tokens.len(), 2);
+	assert_eq!(errors.iter().map(|e| e.code).collect::<Vec<_>>(), [ErrorCode::InvalidDigit, ErrorCode::UnknownEscape]);
+}
+
+#[test]
+fn token_positions() {
+	let source = "(print\n\t\"a\\\nb\" x)\n  :key café 'y";
+	let positions: Vec<_> = Tokenizer::new_from_source(source).tokenize().unwrap().iter().map(|tok| (tok.line, tok.column)).collect();
+
+	/* Tokens are stamped with where they start, even if they span lines */
+	assert_eq!(positions, [(1, 1), (1, 2), (2, 2), (3, 4), (3, 5), (4, 3), (4, 8), (4, 13), (4, 14)]);
+}
+
+#[test]
+fn closing_predicates() {
+	let tokens = Tokenizer::new_from_source("( ) [ ] a \")\" ']").tokenize().unwrap();
+
+	let rparens: Vec<_> = tokens.iter().map(|tok| tok.kind.is_rparen()).collect();
+	let rbrackets: Vec<_> = tokens.iter().map(|tok| tok.kind.is_rbracket()).collect();
+
+	assert_eq!(rparens, [false, true, false, false, false, false, false, false]);
+	assert_eq!(rbrackets, [false, false, false, true, false, false, false, true]);
+}
+
+#[test]
+fn string_continuation() {
+	/* The line break and the indentation after it are dropped */
+	assert_eq!(kinds("\"abc\\\n    def\" \"a\\\r\n\\tb\""), ["String(\"abcdef\")", "String(\"a\\tb\")"]);
+
+	assert_eq!(error_code("\"abc\\"), ErrorCode::UnterminatedString);
+}
+
+#[test]
+fn kept_comments() {
+	let source = "# header\n(f 1) # trailing\r\n#; doc\n#\n";
+	let options = TokenizerOptions{keep_comments: true, ..TokenizerOptions::default()};
+	let tokens = Tokenizer::new_from_source_with_options(source, options).tokenize().unwrap();
+
+	let comments: Vec<_> = tokens.iter().filter_map(|tok| match &tok.kind {
+		TokenKind::Comment(text) => Some((text.as_str(), tok.pos, tok.end, tok.line, tok.column)),
+		_ => None
+	}).collect();
+
+	assert_eq!(comments, [(" header", 0, 8, 1, 1), (" trailing", 15, 25, 2, 7), ("", 34, 35, 4, 1)]);
+
+	/* The parser skips over them */
+	let exprs = Parser::new_from_tokens(&tokens).parse_program().unwrap();
+	assert_eq!(exprs.len(), 1);
+
+	/* And they're dropped without the option */
+	assert_eq!(kinds(source), ["LParen", "Name(\"f\")", "Integer(1, Decimal)", "RParen", "DocComment(\"doc\")"]);
+}
+
+#[test]
+fn unicode_names() {
+	assert_eq!(kinds("café naïve λx x² 日本"), ["Name(\"café\")", "Name(\"naïve\")", "Name(\"λx\")", "Name(\"x²\")", "Name(\"日本\")"]);
+}
+
+#[test]
+fn partial_tokens() {
+	let (tokens, errors) = Tokenizer::new_from_source("(print 1 \"abc").tokenize_partial();
+	let kinds: Vec<_> = tokens.iter().map(|tok| format!("{:?}", tok.kind)).collect();
+
+	assert_eq!(kinds, ["LParen", "Name(\"print\")", "Integer(1, Decimal)"]);
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].code, ErrorCode::UnterminatedString);
+}
+
+#[test]
+fn line_break_in_string() {
+	/* The string ends at the line break, and the next line is read from its start */
+	let (tokens, errors) = Tokenizer::new_from_source("(x \"abc\nfoo bar)").tokenize_partial();
+	let kinds: Vec<_> = tokens.iter().map(|tok| format!("{:?}", tok.kind)).collect();
+
+	assert_eq!(kinds, ["LParen", "Name(\"x\")", "Name(\"foo\")", "Name(\"bar\")", "RParen"]);
+	assert_eq!(errors.len(), 1);
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn big_integers() {
+	let literal = "1234567890123456789012345678901234567890";
+	let tokens = Tokenizer::new_from_source(&format!("{} -{} 0x{}", literal, literal, "F".repeat(20))).tokenize().unwrap();
+
+	let printed: Vec<_> = tokens.iter().map(|tok| match &tok.kind {
+		TokenKind::BigInteger(value) => value.to_string(),
+		other => panic!("Expected a big integer, got {:?}", other)
+	}).collect();
+
+	assert_eq!(printed, [literal.to_string(), format!("-{}", literal), "1208925819614629174706175".to_string()]);
+}
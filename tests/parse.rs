@@ -0,0 +1,131 @@
+use mcf::code::ErrorCode;
+use mcf::lex::{Radix, Tokenizer};
+use mcf::{Expr, Parser, RESERVED_WORDS};
+
+fn parse(source: &str) -> Vec<Expr> {
+	let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+	Parser::new_from_tokens(&tokens).parse_program().unwrap().into_iter().map(|expr| expr.node).collect()
+}
+
+fn docs(exprs: &[Expr]) -> Vec<Option<&str>> {
+	exprs.iter().filter_map(|expr| match expr {
+		Expr::DefineFn{doc, ..} => Some(doc.as_deref()),
+		_ => None
+	}).collect()
+}
+
+#[test]
+fn doc_comments() {
+	let exprs = parse("\
+#; Adds one
+#; to x
+(fn inc (args x) (+ x 1))
+# not a doc comment
+(fn dec (args x) (- x 1))
+#; Stray, nothing to document
+(print 1)
+(fn id (args x) x)
+#; Trailing");
+
+	assert_eq!(docs(&exprs), [Some("Adds one\nto x"), None, None]);
+}
+
+/* Forms cut off by the end of the input are reported at their opening parenthesis */
+#[test]
+fn truncated_forms() {
+	let cases = [
+		("(foo", 0), ("(foo 1", 0), ("(foo :key 1", 0), ("(do", 0), ("(do (f)", 0),
+		("(args", 0), ("(args a", 0), ("(fn f (args) (g", 13), ("(f (g 1) (h", 9), ("  (let x", 2)
+	];
+
+	for &(source, pos) in &cases {
+		let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+		let error = Parser::new_from_tokens(&tokens).parse_program().unwrap_err();
+
+		assert_eq!(error.code, ErrorCode::Unclosed, "{}", source);
+		assert_eq!(error.token.pos, pos, "{}", source);
+	}
+}
+
+#[test]
+fn reserved_names() {
+	for word in RESERVED_WORDS {
+		for (source, pos) in vec![(format!("(fn {} (args) 1)", word), 4), (format!("(let {} int)", word), 5)] {
+			let tokens = Tokenizer::new_from_source(&source).tokenize().unwrap();
+			let error = Parser::new_from_tokens(&tokens).parse_program().unwrap_err();
+
+			assert_eq!(error.code, ErrorCode::ReservedName, "{}", source);
+			assert_eq!(error.token.pos, pos, "{}", source);
+		}
+	}
+}
+
+#[test]
+fn and_or() {
+	let exprs = parse("(and) (or) (and a (f) b) (or a (and b))");
+
+	let lengths: Vec<_> = exprs.iter().map(|expr| match expr {
+		Expr::And{exprs} => ("and", exprs.len()),
+		Expr::Or{exprs} => ("or", exprs.len()),
+		other => panic!("Expected `and` or `or`, got {}", other)
+	}).collect();
+
+	assert_eq!(lengths, [("and", 0), ("or", 0), ("and", 3), ("or", 2)]);
+}
+
+#[test]
+fn program() {
+	let exprs = parse("(let x int)\n(set! x 1)\n\n# done\n(print x) 2");
+
+	let printed: Vec<_> = exprs.iter().map(|expr| expr.to_string()).collect();
+	assert_eq!(printed, ["(let x int)", "(set! x 1)", "(print x)", "2"]);
+
+	assert!(parse("").is_empty());
+	assert!(parse("  # only a comment\n").is_empty());
+}
+
+#[test]
+fn booleans() {
+	let exprs = parse("true false truest");
+
+	assert!(matches!(exprs[0], Expr::BoolLiteral(true)));
+	assert!(matches!(exprs[1], Expr::BoolLiteral(false)));
+	assert!(matches!(&exprs[2], Expr::VariableRef{var} if var == "truest"));
+}
+
+#[test]
+fn nil() {
+	let exprs = parse("nil (do) 'nil nils");
+
+	assert!(matches!(exprs[0], Expr::Nil));
+	assert!(matches!(&exprs[1], Expr::Do{exprs} if exprs.is_empty()));
+	assert!(matches!(&exprs[2], Expr::Quote{expr} if matches!(expr.node, Expr::Nil)));
+	assert!(matches!(&exprs[3], Expr::VariableRef{var} if var == "nils"));
+}
+
+#[test]
+fn typed_args() {
+	let params = |source: &str| match parse(source).remove(0) {
+		Expr::Args{args, ..} => args,
+		other => panic!("Expected an argument list, got {}", other)
+	};
+
+	let name = |name: &str, r#type: Option<&str>| (name.to_string(), r#type.map(str::to_string));
+
+	assert_eq!(params("(args a b)"), [name("a", None), name("b", None)]);
+	assert_eq!(params("(args (a int) (b string))"), [name("a", Some("int")), name("b", Some("string"))]);
+	assert_eq!(params("(args a (b int) c)"), [name("a", None), name("b", Some("int")), name("c", None)]);
+}
+
+#[test]
+fn integer_radix() {
+	let exprs = parse("0xFF 0b101 0o17 255");
+
+	assert!(matches!(exprs[0], Expr::IntegerLiteral(255, Radix::Hexadecimal)));
+	assert!(matches!(exprs[1], Expr::IntegerLiteral(5, Radix::Binary)));
+	assert!(matches!(exprs[2], Expr::IntegerLiteral(15, Radix::Octal)));
+	assert!(matches!(exprs[3], Expr::IntegerLiteral(255, Radix::Decimal)));
+
+	let printed: Vec<_> = exprs.iter().map(|expr| expr.to_string()).collect();
+	assert_eq!(printed, ["0xFF", "0b101", "0o17", "255"]);
+}
@@ -0,0 +1,50 @@
+use mcf::lex::{self, Token, Tokenizer};
+
+/* Applies an edit and checks that re-tokenizing from the restart offset gives
+ * the same tokens as tokenizing the whole edited source, returning the offset. */
+fn relex(source: &str, edit_start: usize, edit_end: usize, replacement: &str) -> usize {
+	let old = Tokenizer::new_from_source(source).tokenize().unwrap();
+	let edited = format!("{}{}{}", &source[..edit_start], replacement, &source[edit_end..]);
+
+	let start = lex::restart_offset(&old, edit_start);
+	assert!(start <= edit_start);
+
+	let mut tokens: Vec<Token> = old.into_iter().take_while(|tok| tok.end <= start).collect();
+	tokens.extend(Tokenizer::new_from_source_at(&edited, start).tokenize().unwrap());
+
+	let expected = Tokenizer::new_from_source(&edited).tokenize().unwrap();
+	assert_eq!(format!("{:?}", tokens), format!("{:?}", expected));
+
+	start
+}
+
+#[test]
+fn edit_outside_string() {
+	let source = "(fn f (args a)\n\t(print a \"text\"))";
+	let pos = source.find("print").unwrap();
+
+	assert_eq!(relex(source, pos + 5, pos + 5, "ln"), pos);
+}
+
+#[test]
+fn edit_inside_string() {
+	let source = "(print 1)\n(print \"hello world\" 2)";
+	let pos = source.find("world").unwrap();
+
+	assert_eq!(relex(source, pos, pos, "big "), source.find(" \"hello").unwrap());
+}
+
+#[test]
+fn edit_opening_string() {
+	let source = "(print a)\n(print b \"c\")";
+	let pos = source.find('a').unwrap();
+
+	relex(source, pos, pos + 1, "\"a b\"");
+}
+
+#[test]
+fn edit_at_start() {
+	let source = "(print 1)";
+
+	assert_eq!(relex(source, 0, 0, "# comment\n"), 0);
+}
@@ -0,0 +1,80 @@
+use mcf::lex::{TokenKind, Tokenizer, TokenizerOptions};
+use mcf::{Expr, Parser, SourceFile, SourceMap};
+
+#[test]
+fn line_column() {
+	let file = SourceFile::new_from_source("(a\r\n  b)\n\"é\" c\n");
+
+	assert_eq!(file.line_count(), 4);
+	assert_eq!(file.line_column(0), (1, 1));
+	assert_eq!(file.line_column(6), (2, 3));
+	assert_eq!(file.line_column(14), (3, 5));
+	assert_eq!(file.line_content(2), "  b)");
+}
+
+#[test]
+fn past_the_end() {
+	let file = SourceFile::new_from_source("(a\r\nb)\n");
+
+	/* The line break of a line, and the end of a file ending in one, both
+	 * point just after the last character of the line */
+	assert_eq!(file.line_column(2), (1, 3));
+	assert_eq!(file.line_column(7), (2, 3));
+	assert_eq!(file.line_column(100), (2, 3));
+}
+
+#[test]
+fn source_map() {
+	let mut map = SourceMap::default();
+	let a = map.add("a.mcf", "(print 1)\n");
+	let b = map.add("b.mcf", "(print\n  x)");
+
+	let options = TokenizerOptions{base_offset: map.file(b).base_offset(), ..TokenizerOptions::default()};
+	let tokens = Tokenizer::new_from_source_with_options(map.file(b).source(), options).tokenize().unwrap();
+	let x = tokens.iter().find(|tok| matches!(&tok.kind, TokenKind::Name(name) if name == "x")).unwrap();
+
+	let (id, pos) = map.resolve(x.pos).unwrap();
+	assert_eq!(map.name(id), "b.mcf");
+	assert_eq!((pos.line, pos.column), (2, 3));
+
+	assert_eq!(map.file_of(0), Some(a));
+	assert_eq!(map.file_of(10), Some(a));
+	assert_eq!(map.file_of(11), Some(b));
+	assert_eq!(map.file_of(100), None);
+}
+
+#[test]
+fn nested_span() {
+	let source = "(+ 1 (* 2 3))";
+	let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+	let expr = Parser::new_from_tokens(&tokens).parse_expr().unwrap().unwrap();
+
+	assert_eq!(&source[expr.span.start..expr.span.end], source);
+
+	let inner = match &expr.node {
+		Expr::FnCall{args, ..} => &args[1],
+		_ => panic!("Expected a function call, got {}", expr)
+	};
+
+	assert_eq!(&source[inner.span.start..inner.span.end], "(* 2 3)");
+}
+
+#[test]
+fn base_offset() {
+	let source = "(a \"b\"\n  c)";
+	let options = TokenizerOptions{base_offset: 100, ..TokenizerOptions::default()};
+
+	let plain = Tokenizer::new_from_source(source).tokenize().unwrap();
+	let shifted = Tokenizer::new_from_source_with_options(source, options).tokenize().unwrap();
+
+	for (plain, shifted) in plain.iter().zip(&shifted) {
+		assert_eq!((shifted.pos, shifted.end), (plain.pos + 100, plain.end + 100));
+		assert_eq!((shifted.line, shifted.column), (plain.line, plain.column));
+	}
+
+	let error = Tokenizer::new_from_source_with_options("(a\n  \\)", options).tokenize().unwrap_err();
+	assert_eq!(error.pos, 105);
+
+	let file = SourceFile::new_from_source_with_base(source, 100);
+	assert_eq!(file.line_column(shifted[3].pos), (2, 3));
+}
@@ -0,0 +1,151 @@
+use std::thread;
+
+use mcf::code::ErrorCode;
+use mcf::diagnostic::{self, Severity};
+use mcf::{expand, lex, Expr, Parser, SourceFile, DEFAULT_MAX_DEPTH};
+
+const ELEMENTS: usize = 100_000;
+const STACK_SIZE: usize = 256 * 1024;
+
+/* Long but flat lists should only take stack for their nesting, not their length,
+ * so parse them on a small stack and return the single resulting expression. */
+fn parse_flat(head: &str, element: fn(usize) -> String, tail: &str) -> Expr {
+	let mut source = head.to_string();
+
+	for i in 0..ELEMENTS {
+		source.push(' ');
+		source.push_str(&element(i));
+	}
+
+	source.push_str(tail);
+
+	let handle = thread::Builder::new().stack_size(STACK_SIZE).spawn(move || {
+		let tokens = lex::Tokenizer::new_from_source(&source).tokenize().unwrap();
+		let exprs = Parser::new_from_tokens(&tokens).parse_program().unwrap();
+		let mut exprs = expand::expand_macros(exprs).unwrap();
+
+		assert_eq!(exprs.len(), 1);
+		exprs.pop().unwrap().node
+	}).unwrap();
+
+	handle.join().unwrap()
+}
+
+#[test]
+fn flat_do() {
+	match parse_flat("(do", |i| format!("(print {})", i), ")") {
+		Expr::Do{exprs} => assert_eq!(exprs.len(), ELEMENTS),
+		other => panic!("Expected a `do`, got {:?}", other)
+	}
+}
+
+#[test]
+fn flat_call() {
+	match parse_flat("(f", |i| i.to_string(), ")") {
+		Expr::FnCall{args, ..} => assert_eq!(args.len(), ELEMENTS),
+		other => panic!("Expected a call, got {:?}", other)
+	}
+}
+
+#[test]
+fn flat_args() {
+	match parse_flat("(fn f (args", |i| format!("a{}", i), ") nil)") {
+		Expr::DefineFn{args, ..} => match args.node {
+			Expr::Args{args, ..} => assert_eq!(args.len(), ELEMENTS),
+			other => panic!("Expected an argument list, got {:?}", other)
+		},
+		other => panic!("Expected a function, got {:?}", other)
+	}
+}
+
+const NESTING: usize = 20_000;
+
+/* Nesting past the limit must be a clean error at the token that went over it, not a stack overflow */
+fn parse_nested(open: &str, close: &str, error_pos: usize) {
+	let source = format!("{}a{}", open.repeat(NESTING), close.repeat(NESTING));
+	let tokens = lex::Tokenizer::new_from_source(&source).tokenize().unwrap();
+
+	let error = Parser::new_from_tokens(&tokens).parse_program().unwrap_err();
+	assert_eq!(error.code, ErrorCode::NestingTooDeep);
+	assert_eq!(error.token.pos, error_pos);
+}
+
+#[test]
+fn deep_parens() {
+	parse_nested("(f ", ")", DEFAULT_MAX_DEPTH * 3);
+}
+
+#[test]
+fn deep_quotes() {
+	parse_nested("'", "", DEFAULT_MAX_DEPTH);
+}
+
+#[test]
+fn deep_mixed() {
+	/* Each repetition nests twice, so the limit is hit at the quote half way through */
+	parse_nested("'(f ", ")", DEFAULT_MAX_DEPTH / 2 * 4);
+}
+
+/* Every error is resolved to a line through the line index, which has to stay
+ * fast when there are lots of errors in a large file */
+#[test]
+fn many_errors() {
+	let source = "(print a\\b)\n".repeat(ELEMENTS);
+	let file = SourceFile::new_from_source(&source);
+
+	let (_, errors) = lex::Tokenizer::new_from_source(&source).tokenize_partial();
+	assert_eq!(errors.len(), ELEMENTS);
+
+	let last = diagnostic::format_error(Severity::Error, "many.mcf", &file, errors.last().unwrap(), false);
+	assert!(last.starts_with(&format!("many.mcf:{}:9: ", ELEMENTS)), "Unexpected diagnostic {}", last);
+
+	for error in &errors {
+		diagnostic::format_error(Severity::Error, "many.mcf", &file, error, false);
+	}
+}
+
+/* xorshift64, enough to spread inputs around without pulling in a crate */
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	fn below(&mut self, n: usize) -> usize {
+		(self.next() % n as u64) as usize
+	}
+}
+
+const FRAGMENTS: &[&str] = &[
+	"(", ")", "[", "]", "\"", "\\", "'", "`", ",", ",@", "#", "#;", ";", ":", ".", "..", "&rest",
+	"0", "1", "9", "0x", "0b", "0o", "e", "-", "+", "1e999", "99999999999999999999",
+	"a", "x'", "fn", "let", "args", "do", "nil", "true", " ", "\n", "\r", "\t", "\0", "é", "中", "\u{1f600}"
+];
+
+/* Arbitrary input must always come back as tokens or errors, never a panic */
+#[test]
+fn random_input() {
+	let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+	for _ in 0..20_000 {
+		let mut source = String::new();
+
+		for _ in 0..rng.below(40) {
+			match rng.below(8) {
+				0 => source.push(std::char::from_u32(rng.below(0x800) as u32).unwrap_or('?')),
+				_ => source.push_str(FRAGMENTS[rng.below(FRAGMENTS.len())])
+			}
+		}
+
+		let (tokens, _) = lex::Tokenizer::new_from_source(&source).tokenize_partial();
+		let _ = Parser::new_from_tokens(&tokens).parse_program_recovering();
+
+		if let Ok(tokens) = lex::Tokenizer::new_from_source(&source).tokenize() {
+			let _ = Parser::new_from_tokens(&tokens).parse_program();
+		}
+	}
+}
@@ -0,0 +1,10 @@
+use mcf::util::Fill;
+
+#[test]
+fn fill() {
+	assert_eq!(Fill::with(0, '~').to_string(), "");
+	assert_eq!(Fill::with(1, '~').to_string(), "~");
+	assert_eq!(Fill::with(5, ' ').to_string(), "     ");
+	assert_eq!(Fill::with(3, 'é').to_string(), "ééé");
+	assert_eq!(format!("[{}]", Fill::with(2, '-')), "[--]");
+}
@@ -0,0 +1,40 @@
+use mcf::lex::Tokenizer;
+use mcf::span::Spanned;
+use mcf::visit::{collect_variable_refs, walk_expr, Visitor};
+use mcf::{Expr, Parser};
+
+fn parse(source: &str) -> Spanned<Expr> {
+	let tokens = Tokenizer::new_from_source(source).tokenize().unwrap();
+	Parser::new_from_tokens(&tokens).parse_expr().unwrap().unwrap()
+}
+
+#[test]
+fn variable_refs() {
+	let source = "(fn f (args a b) (do (print a :to out) (let* ((c (+ a b))) (when c (set! d e)))))";
+	let expr = parse(source);
+
+	let refs: Vec<_> = collect_variable_refs(&expr).into_iter().map(|(name, span)| (name, &source[span.start..span.end])).collect();
+	assert_eq!(refs, [("a", "a"), ("out", "out"), ("a", "a"), ("b", "b"), ("c", "c"), ("e", "e")]);
+}
+
+/* Overriding one hook still visits everything below the ones left alone */
+#[derive(Default)]
+struct CallNames(Vec<String>);
+
+impl<'ast> Visitor<'ast> for CallNames {
+	fn visit_fncall(&mut self, expr: &'ast Spanned<Expr>) {
+		if let Expr::FnCall{name, ..} = &expr.node {
+			self.0.push(name.clone());
+		}
+
+		walk_expr(self, expr);
+	}
+}
+
+#[test]
+fn custom_visitor() {
+	let mut names = CallNames::default();
+	names.visit_expr(&parse("(module m (when (f (g 1)) (and (h) `(i ,(j)))))"));
+
+	assert_eq!(names.0, ["f", "g", "h", "i", "j"]);
+}